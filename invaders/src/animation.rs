@@ -0,0 +1,147 @@
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    // Starts over after the last frame
+    Loop,
+    // Stays on the last frame once it is over
+    Once,
+}
+
+// Frames of a sprite shown one after the other, each for its own duration. Frames are usually
+// glyphs, or indices into a list of glyphs.
+#[derive(Clone, Debug)]
+pub struct Animation<T> {
+    frames: Vec<(T, Duration)>,
+    repeat: Repeat,
+    index: usize,
+    // Time spent on the current frame
+    elapsed: Duration,
+    finished: bool,
+}
+
+impl<T: Copy> Animation<T> {
+    // There must be at least one frame
+    pub fn new(frames: Vec<(T, Duration)>, repeat: Repeat) -> Self {
+        assert!(!frames.is_empty(), "an animation needs frames");
+        Self {
+            frames,
+            repeat,
+            index: 0,
+            elapsed: Duration::ZERO,
+            finished: false,
+        }
+    }
+
+    // All frames shown for the same duration
+    pub fn even(frames: &[T], each: Duration, repeat: Repeat) -> Self {
+        Self::new(frames.iter().map(|&frame| (frame, each)).collect(), repeat)
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        if self.finished {
+            return;
+        }
+        self.elapsed += delta;
+        // Zero-length frames are skipped over, unless they all are
+        let mut skipped = 0;
+        while self.elapsed >= self.frames[self.index].1 && skipped <= self.frames.len() {
+            self.elapsed -= self.frames[self.index].1;
+            skipped += 1;
+            if self.index + 1 < self.frames.len() {
+                self.index += 1;
+            } else if self.repeat == Repeat::Loop {
+                self.index = 0;
+            } else {
+                self.elapsed = Duration::ZERO;
+                self.finished = true;
+                return;
+            }
+        }
+    }
+
+    pub fn current(&self) -> T {
+        self.frames[self.index].0
+    }
+
+    // Only ever true for animations played once
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn uneven(repeat: Repeat) -> Animation<char> {
+        Animation::new(vec![('a', ms(100)), ('b', ms(50)), ('c', ms(200))], repeat)
+    }
+
+    #[test]
+    fn each_frame_lasts_its_own_duration() {
+        let mut animation = uneven(Repeat::Loop);
+        let steps = [
+            (60, 'a'),
+            (40, 'b'),
+            (49, 'b'),
+            (1, 'c'),
+            (199, 'c'),
+            (1, 'a'),
+        ];
+        for (delta, expected) in steps {
+            animation.update(ms(delta));
+            assert_eq!(animation.current(), expected, "after {} ms", delta);
+        }
+        assert!(!animation.finished());
+    }
+
+    #[test]
+    fn a_long_delta_goes_through_several_frames() {
+        let mut animation = uneven(Repeat::Loop);
+        // A whole loop, then into the second frame
+        animation.update(ms(100 + 50 + 200 + 100 + 10));
+        assert_eq!(animation.current(), 'b');
+        // What was left over counts towards the next frame
+        animation.update(ms(40));
+        assert_eq!(animation.current(), 'c');
+    }
+
+    #[test]
+    fn an_animation_played_once_stays_on_its_last_frame() {
+        let mut animation = uneven(Repeat::Once);
+        animation.update(ms(349));
+        assert_eq!(animation.current(), 'c');
+        assert!(!animation.finished());
+        animation.update(ms(1));
+        assert!(animation.finished());
+        animation.update(ms(1000));
+        assert_eq!(animation.current(), 'c');
+
+        let mut animation = uneven(Repeat::Once);
+        animation.update(ms(5000));
+        assert_eq!(animation.current(), 'c');
+        assert!(animation.finished());
+    }
+
+    #[test]
+    fn zero_length_frames_are_skipped() {
+        let mut animation = Animation::new(vec![('a', ms(0)), ('b', ms(100))], Repeat::Loop);
+        animation.update(ms(0));
+        assert_eq!(animation.current(), 'b');
+        animation.update(ms(100));
+        assert_eq!(animation.current(), 'b');
+
+        // Even when they all are, without looping forever
+        let mut animation = Animation::even(&['a', 'b'], ms(0), Repeat::Loop);
+        animation.update(ms(10));
+        let mut animation = Animation::even(&['a', 'b'], ms(0), Repeat::Once);
+        animation.update(ms(0));
+        assert!(animation.finished());
+        assert_eq!(animation.current(), 'b');
+    }
+}
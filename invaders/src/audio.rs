@@ -0,0 +1,150 @@
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sound {
+    Explode,
+    Lose,
+    Move,
+    Pew,
+    Startup,
+    Win,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 6] = [
+        Sound::Explode,
+        Sound::Lose,
+        Sound::Move,
+        Sound::Pew,
+        Sound::Startup,
+        Sound::Win,
+    ];
+
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Sound::Explode => "explode.wav",
+            Sound::Lose => "lose.wav",
+            Sound::Move => "move.wav",
+            Sound::Pew => "pew.wav",
+            Sound::Startup => "startup.wav",
+            Sound::Win => "win.wav",
+        }
+    }
+}
+
+// Where sounds go, the game behaves the same whether anything is heard or not
+pub trait AudioSink {
+    fn play(&mut self, sound: Sound);
+
+    // Blocks until the sounds already playing are done
+    fn wait(&self);
+}
+
+// For machines without an audio device, or builds without the audio feature
+pub struct NoAudio;
+
+impl AudioSink for NoAudio {
+    fn play(&mut self, _sound: Sound) {}
+
+    fn wait(&self) {}
+}
+
+// Plays through the default audio device
+#[cfg(feature = "audio")]
+pub struct Speakers {
+    audio: rusty_audio::Audio,
+    loaded: Vec<Sound>,
+}
+
+#[cfg(feature = "audio")]
+impl Speakers {
+    // None without an audio device, sounds whose file cannot be found stay silent
+    pub fn new() -> Option<Self> {
+        let mut audio = rusty_audio::Audio::new();
+        if audio.disabled() {
+            return None;
+        }
+
+        let mut loaded = Vec::new();
+        for sound in Sound::ALL {
+            if let Some(path) = asset_path(sound.file_name()) {
+                audio.add(sound.file_name(), path);
+                loaded.push(sound);
+            }
+        }
+        Some(Self { audio, loaded })
+    }
+}
+
+#[cfg(feature = "audio")]
+impl AudioSink for Speakers {
+    fn play(&mut self, sound: Sound) {
+        if self.loaded.contains(&sound) {
+            self.audio.play(sound.file_name());
+        }
+    }
+
+    fn wait(&self) {
+        self.audio.wait();
+    }
+}
+
+// Any sink, with sound that can be switched off while playing
+pub struct Audio {
+    sink: Box<dyn AudioSink>,
+    muted: bool,
+}
+
+impl Audio {
+    pub fn new(sink: Box<dyn AudioSink>, muted: bool) -> Self {
+        Self { sink, muted }
+    }
+
+    // The audio device when there is one and the game was built with audio, silence otherwise
+    pub fn open(muted: bool) -> Self {
+        #[cfg(feature = "audio")]
+        if let Some(speakers) = Speakers::new() {
+            return Self::new(Box::new(speakers), muted);
+        }
+        Self::new(Box::new(NoAudio), muted)
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+    }
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new(Box::new(NoAudio), false)
+    }
+}
+
+impl AudioSink for Audio {
+    fn play(&mut self, sound: Sound) {
+        if !self.muted {
+            self.sink.play(sound);
+        }
+    }
+
+    fn wait(&self) {
+        self.sink.wait();
+    }
+}
+
+// Sound files are looked up next to the executable, then in the working directory, then in the
+// sources the game was built from, for `cargo run`
+pub fn asset_path(file_name: &str) -> Option<PathBuf> {
+    let exe_dir = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from));
+    let cwd = env::current_dir().ok();
+    let source_dir = Some(PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+
+    [exe_dir, cwd, source_dir]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(file_name))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,356 @@
+use crate::game::Command;
+use crate::paths;
+use crossterm::event::KeyCode;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const FILE_NAME: &str = "keys.txt";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Fire,
+    Pause,
+    Quit,
+    Mute,
+    // The second player's, in co-op
+    MoveLeft2,
+    MoveRight2,
+    Fire2,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Fire,
+        Action::Pause,
+        Action::Quit,
+        Action::Mute,
+        Action::MoveLeft2,
+        Action::MoveRight2,
+        Action::Fire2,
+    ];
+
+    // Name in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::Fire => "fire",
+            Action::Pause => "pause",
+            Action::Quit => "quit",
+            Action::Mute => "mute",
+            Action::MoveLeft2 => "left2",
+            Action::MoveRight2 => "right2",
+            Action::Fire2 => "fire2",
+        }
+    }
+
+    // The player and the command, for actions controlling a player
+    pub fn command(self) -> Option<(usize, Command)> {
+        match self {
+            Action::MoveLeft => Some((0, Command::MoveLeft)),
+            Action::MoveRight => Some((0, Command::MoveRight)),
+            Action::Fire => Some((0, Command::Shoot)),
+            Action::MoveLeft2 => Some((1, Command::MoveLeft)),
+            Action::MoveRight2 => Some((1, Command::MoveRight)),
+            Action::Fire2 => Some((1, Command::Shoot)),
+            Action::Pause | Action::Quit | Action::Mute => None,
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+#[derive(Debug)]
+pub enum BindingsError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl Display for BindingsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingsError::Io(err) => write!(f, "{}", err),
+            BindingsError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for BindingsError {}
+
+impl From<io::Error> for BindingsError {
+    fn from(err: io::Error) -> Self {
+        BindingsError::Io(err)
+    }
+}
+
+fn syntax_error(line: usize, message: impl Into<String>) -> BindingsError {
+    BindingsError::Syntax {
+        line,
+        message: message.into(),
+    }
+}
+
+// Keys of each action, a key belongs to a single action
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Bindings {
+    keys: Vec<(Action, Vec<KeyCode>)>,
+}
+
+impl Bindings {
+    pub fn default_path() -> Option<PathBuf> {
+        paths::config_dir().map(|dir| dir.join(FILE_NAME))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BindingsError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, BindingsError> {
+        let mut keys: Vec<(Action, Vec<KeyCode>)> = Vec::new();
+        let mut last_line = 0;
+        for (idx, line) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            last_line = line_no;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((name, names)) = line.split_once('=') else {
+                return Err(syntax_error(line_no, "expected `action = key ...`"));
+            };
+            let name = name.trim();
+            let action = Action::from_name(name).ok_or_else(|| {
+                syntax_error(
+                    line_no,
+                    format!(
+                        "unknown action `{}`, expected left, right, fire, pause, quit, mute, left2, right2 or fire2",
+                        name
+                    ),
+                )
+            })?;
+            if keys.iter().any(|(bound, _)| *bound == action) {
+                return Err(syntax_error(line_no, format!("`{}` is set twice", name)));
+            }
+
+            let mut action_keys = Vec::new();
+            for key_name in names.split_whitespace() {
+                let key = parse_key(key_name)
+                    .ok_or_else(|| syntax_error(line_no, format!("unknown key `{}`", key_name)))?;
+                let other = keys
+                    .iter()
+                    .find(|(_, bound)| bound.contains(&key))
+                    .map(|(other, _)| *other)
+                    .or_else(|| action_keys.contains(&key).then_some(action));
+                if let Some(other) = other {
+                    return Err(syntax_error(
+                        line_no,
+                        format!("key `{}` is already bound to {}", key_name, other.name()),
+                    ));
+                }
+                action_keys.push(key);
+            }
+            if action_keys.is_empty() {
+                return Err(syntax_error(line_no, format!("`{}` has no keys", name)));
+            }
+            keys.push((action, action_keys));
+        }
+
+        // Actions left out keep their default keys, as long as nothing else took them
+        let defaults = Self::default();
+        for action in Action::ALL {
+            if keys.iter().any(|(bound, _)| *bound == action) {
+                continue;
+            }
+            let free: Vec<KeyCode> = defaults
+                .keys(action)
+                .iter()
+                .copied()
+                .filter(|key| !keys.iter().any(|(_, bound)| bound.contains(key)))
+                .collect();
+            // Every action must stay reachable, co-op ones only matter when playing co-op
+            let co_op = matches!(action.command(), Some((1, _)));
+            if free.is_empty() && !co_op {
+                return Err(syntax_error(
+                    last_line.max(1),
+                    format!("no keys for `{}`", action.name()),
+                ));
+            }
+            keys.push((action, free));
+        }
+
+        Ok(Self { keys })
+    }
+
+    pub fn action(&self, key: KeyCode) -> Option<Action> {
+        self.keys
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            keys: vec![
+                (Action::MoveLeft, vec![KeyCode::Left]),
+                (Action::MoveRight, vec![KeyCode::Right]),
+                (Action::Fire, vec![KeyCode::Char(' '), KeyCode::Enter]),
+                (Action::Pause, vec![KeyCode::Char('p'), KeyCode::Esc]),
+                (Action::Quit, vec![KeyCode::Char('q')]),
+                (Action::Mute, vec![KeyCode::Char('m')]),
+                (Action::MoveLeft2, vec![KeyCode::Char('a')]),
+                (Action::MoveRight2, vec![KeyCode::Char('d')]),
+                (Action::Fire2, vec![KeyCode::Char('w')]),
+            ],
+        }
+    }
+}
+
+// Written in the format of the config file
+impl Display for Bindings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# Keys of each action, separated by spaces")?;
+        writeln!(
+            f,
+            "# Keys are single characters or Left, Right, Up, Down, Space, Enter, Esc, Tab, Backspace"
+        )?;
+        for (action, keys) in self.keys.iter() {
+            let names: Vec<String> = keys.iter().map(|key| key_name(*key)).collect();
+            writeln!(f, "{} = {}", action.name(), names.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let key = match name {
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Space" => KeyCode::Char(' '),
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+fn key_name(key: KeyCode) -> String {
+    match key {
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (usize, String) {
+        match Bindings::parse(text).unwrap_err() {
+            BindingsError::Syntax { line, message } => (line, message),
+            BindingsError::Io(err) => panic!("unexpected {}", err),
+        }
+    }
+
+    #[test]
+    fn the_default_config_parses_back_to_the_defaults() {
+        let text = Bindings::default().to_string();
+        assert_eq!(Bindings::parse(&text).unwrap(), Bindings::default());
+    }
+
+    #[test]
+    fn an_empty_config_gives_the_defaults() {
+        let bindings = Bindings::parse("# nothing here\n\n").unwrap();
+        for action in Action::ALL {
+            assert_eq!(bindings.keys(action), Bindings::default().keys(action));
+        }
+    }
+
+    #[test]
+    fn a_key_belongs_to_one_action() {
+        assert_eq!(
+            error("fire = Space\nleft = h Space"),
+            (2, "key `Space` is already bound to fire".to_string())
+        );
+        assert_eq!(
+            error("left = h h"),
+            (1, "key `h` is already bound to left".to_string())
+        );
+    }
+
+    #[test]
+    fn mistakes_are_reported_on_their_line() {
+        let cases = [
+            ("left = h\nleft = j", (2, "`left` is set twice")),
+            (
+                "jump = k",
+                (
+                    1,
+                    "unknown action `jump`, expected left, right, fire, pause, quit, mute, left2, right2 or fire2",
+                ),
+            ),
+            ("# keys\nleft = Home", (2, "unknown key `Home`")),
+            ("fire =", (1, "`fire` has no keys")),
+            ("fire Space", (1, "expected `action = key ...`")),
+        ];
+        for (text, (line, message)) in cases {
+            assert_eq!(error(text), (line, message.to_string()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn defaults_taken_by_another_action_are_dropped() {
+        let bindings = Bindings::parse("fire = Space p").unwrap();
+        assert_eq!(bindings.action(KeyCode::Char('p')), Some(Action::Fire));
+        assert_eq!(bindings.keys(Action::Pause), [KeyCode::Esc]);
+        // Enter was only a default of fire, which now has its own keys
+        assert_eq!(bindings.action(KeyCode::Enter), None);
+
+        // Unless that leaves an action with no keys at all
+        assert_eq!(
+            error("fire = Space\n\nmute = q"),
+            (3, "no keys for `quit`".to_string())
+        );
+        // Co-op actions may end up without keys
+        let bindings = Bindings::parse("fire = w").unwrap();
+        assert!(bindings.keys(Action::Fire2).is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+use crate::frame::{Cell, Color, Drawable, Frame, Layer, Style};
+use crate::invaders::Invaders;
+use crate::playfield::Playfield;
+use crate::shot::Shot;
+use std::vec::Drain;
+
+const BUNKER_COUNT: usize = 4;
+const BUNKER_WIDTH: usize = 4;
+const BUNKER_HEIGHT: usize = 2;
+// Number of hits a cell takes before it is gone
+const CELL_HEALTH: u8 = 3;
+
+pub struct BunkerCell {
+    pub x: usize,
+    pub y: usize,
+    health: u8,
+}
+
+pub struct Bunkers {
+    pub cells: Vec<BunkerCell>,
+    // Cells hit since the last `drain_hits`
+    hits: Vec<(usize, usize)>,
+}
+
+impl Bunkers {
+    pub fn new(playfield: Playfield) -> Self {
+        let mut cells = Vec::new();
+        // Evenly spaced, just above the player row with one free row in between
+        let gap = (playfield.cols - BUNKER_COUNT * BUNKER_WIDTH) / (BUNKER_COUNT + 1);
+        let top = playfield.bottom() - 1 - BUNKER_HEIGHT;
+
+        for bunker in 0..BUNKER_COUNT {
+            let left = gap + bunker * (BUNKER_WIDTH + gap);
+            for x in left..left + BUNKER_WIDTH {
+                for y in top..top + BUNKER_HEIGHT {
+                    cells.push(BunkerCell {
+                        x,
+                        y,
+                        health: CELL_HEALTH,
+                    });
+                }
+            }
+        }
+
+        Self {
+            cells,
+            hits: Vec::new(),
+        }
+    }
+
+    // Erodes the cell at the given position, returning whether there was one
+    pub fn hit_at(&mut self, x: usize, y: usize) -> bool {
+        if let Some(idx) = self
+            .cells
+            .iter()
+            .position(|cell| (cell.x == x) && (cell.y == y))
+        {
+            self.cells[idx].health -= 1;
+            self.hits.push((x, y));
+            if self.cells[idx].health == 0 {
+                self.cells.remove(idx);
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // Stops every live shot that ran into a cell
+    pub fn stop_shots(&mut self, shots: &mut [Shot]) {
+        for shot in shots.iter_mut() {
+            if !shot.exploding && self.hit_at(shot.x, shot.y) {
+                shot.explode();
+            }
+        }
+    }
+
+    pub fn drain_hits(&mut self) -> Drain<'_, (usize, usize)> {
+        self.hits.drain(..)
+    }
+
+    // The army destroys whatever it descends through
+    pub fn crush(&mut self, invaders: &Invaders) {
+        self.cells.retain(|cell| !invaders.covers(cell.x, cell.y));
+    }
+}
+
+impl Default for Bunkers {
+    fn default() -> Self {
+        Self::new(Playfield::default())
+    }
+}
+
+impl Drawable for Bunkers {
+    fn draw(&self, frame: &mut Frame) {
+        for cell in self.cells.iter() {
+            frame[cell.x][cell.y] = match cell.health {
+                3 => Cell::new('#', Style::fg(Color::Green)),
+                2 => Cell::new('=', Style::fg(Color::Green)),
+                _ => Cell::new('-', Style::fg(Color::Green).dim()),
+            };
+        }
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Bunkers
+    }
+}
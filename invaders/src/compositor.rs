@@ -0,0 +1,124 @@
+use crate::frame::{self, Drawable, Frame, Layer};
+use crate::playfield::Playfield;
+
+// Draws layer by layer whatever was added, so that nothing has to know what goes over it.
+// Within a layer, what was added first is drawn first.
+#[derive(Default)]
+pub struct Compositor<'a> {
+    drawables: Vec<Box<dyn Drawable + 'a>>,
+}
+
+impl<'a> Compositor<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, drawable: impl Drawable + 'a) {
+        self.drawables.push(Box::new(drawable));
+    }
+
+    fn stacked(&self) -> Vec<&(dyn Drawable + 'a)> {
+        let mut stacked: Vec<_> = self.drawables.iter().map(|drawable| &**drawable).collect();
+        // Stable, keeping the order within each layer
+        stacked.sort_by_key(|drawable| drawable.layer());
+        stacked
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        for drawable in self.stacked() {
+            drawable.draw(frame);
+        }
+    }
+
+    // Layers under the HUD at twice the vertical resolution, squeezed back into the frame, with
+    // the HUD and overlays on top at the normal resolution so their text stays readable. What
+    // the frame held before is replaced.
+    pub fn draw_half_blocks(&self, frame: &mut Frame) {
+        let mut pixels = frame::new_frame(Playfield {
+            cols: frame.len(),
+            rows: frame.first().map_or(0, |col| col.len()) * 2,
+        });
+        let (under, over): (Vec<_>, Vec<_>) = self
+            .stacked()
+            .into_iter()
+            .partition(|drawable| drawable.layer() < Layer::Hud);
+        for drawable in under {
+            drawable.draw_half_blocks(&mut pixels);
+        }
+        *frame = frame::squeeze(&pixels);
+        for drawable in over {
+            drawable.draw(frame);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Cell, Style};
+
+    // Writes its glyph to the first cell, in lower case when drawn at twice the resolution
+    struct Stub {
+        glyph: char,
+        layer: Layer,
+    }
+
+    impl Drawable for Stub {
+        fn draw(&self, frame: &mut Frame) {
+            frame[0][0] = Cell::new(self.glyph, Style::DEFAULT);
+        }
+
+        fn draw_half_blocks(&self, pixels: &mut Frame) {
+            let cell = Cell::new(self.glyph.to_ascii_lowercase(), Style::DEFAULT);
+            pixels[0][0] = cell;
+            pixels[0][1] = cell;
+        }
+
+        fn layer(&self) -> Layer {
+            self.layer
+        }
+    }
+
+    fn top(stubs: &[(char, Layer)], half_blocks: bool) -> char {
+        let mut compositor = Compositor::new();
+        for &(glyph, layer) in stubs {
+            compositor.add(Stub { glyph, layer });
+        }
+        let mut frame = frame::new_frame(Playfield { cols: 2, rows: 2 });
+        if half_blocks {
+            compositor.draw_half_blocks(&mut frame);
+        } else {
+            compositor.draw(&mut frame);
+        }
+        frame[0][0].glyph
+    }
+
+    #[test]
+    fn higher_layers_go_on_top_whatever_the_order_they_came_in() {
+        let stubs = [
+            ('O', Layer::Overlay),
+            ('H', Layer::Hud),
+            ('E', Layer::Entities),
+            ('B', Layer::Background),
+        ];
+        assert_eq!(top(&stubs, false), 'O');
+        assert_eq!(top(&stubs[1..], false), 'H');
+        assert_eq!(top(&stubs[2..], false), 'E');
+    }
+
+    #[test]
+    fn within_a_layer_the_last_added_goes_on_top() {
+        let stubs = [('A', Layer::Entities), ('B', Layer::Entities)];
+        assert_eq!(top(&stubs, false), 'B');
+        let stubs = [('B', Layer::Entities), ('A', Layer::Entities)];
+        assert_eq!(top(&stubs, false), 'A');
+    }
+
+    #[test]
+    fn only_the_hud_and_overlays_stay_at_the_normal_resolution() {
+        assert_eq!(top(&[('E', Layer::Effects)], true), 'e');
+        assert_eq!(top(&[('H', Layer::Hud), ('E', Layer::Effects)], true), 'H');
+        assert_eq!(top(&[('O', Layer::Overlay)], true), 'O');
+        assert_eq!(top(&[('B', Layer::Background)], true), 'b');
+    }
+}
@@ -0,0 +1,92 @@
+use crate::game::{Command, Game};
+
+// Decides a player's commands each tick, like the keyboard does for a human
+pub trait Controller {
+    fn commands(&mut self, game: &Game, player: usize) -> Vec<Command>;
+}
+
+// Rows above the player where a falling bomb is worth dodging
+const DANGER_ROWS: usize = 5;
+// Ticks between two moves, so that the bot is quick but not superhuman
+const MOVE_TICKS: u32 = 3;
+
+// Goes for the nearest invader column and steps out of the way of bombs
+#[derive(Default)]
+pub struct Bot {
+    wait: u32,
+}
+
+impl Bot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Controller for Bot {
+    fn commands(&mut self, game: &Game, player: usize) -> Vec<Command> {
+        let Some(me) = game.players().get(player).filter(|me| !me.dead()) else {
+            return Vec::new();
+        };
+        let (x, y) = (me.x(), me.y());
+        let cols = game.playfield().cols;
+        // Anywhere under the cannon when it is in the given column
+        let half_width = me.bounds().width / 2;
+        let danger = |x: usize| {
+            game.invaders().bombs.iter().any(|bomb| {
+                !bomb.exploding
+                    && bomb.x.abs_diff(x) <= half_width
+                    && bomb.y <= y
+                    && y - bomb.y <= DANGER_ROWS
+            })
+        };
+        let safe_left = x > half_width && !danger(x - 1);
+        let safe_right = x + half_width + 1 < cols && !danger(x + 1);
+
+        // Middle columns of the invaders
+        let target = game
+            .invaders()
+            .army
+            .iter()
+            .map(|invader| invader.bounds().x + invader.bounds().width / 2)
+            .min_by_key(|invader_x| invader_x.abs_diff(x));
+
+        let mut commands = Vec::new();
+        if target == Some(x) && !danger(x) {
+            commands.push(Command::Shoot);
+        }
+
+        self.wait = self.wait.saturating_sub(1);
+        if self.wait > 0 {
+            return commands;
+        }
+        let step = if danger(x) {
+            // Dodging first, toward the nearest safe spot, which can be more than a step away
+            // for a wide cannon, and toward the target when both sides are as near
+            let reach = |k: usize| {
+                let left = x >= half_width + k && !danger(x - k);
+                let right = x + k + half_width < cols && !danger(x + k);
+                (left || right).then_some((left, right))
+            };
+            let (safe_left, safe_right) = (1..=2 * half_width + 1)
+                .find_map(reach)
+                .unwrap_or((false, false));
+            match (safe_left, safe_right) {
+                (true, true) if target.is_some_and(|target| target > x) => Some(Command::MoveRight),
+                (true, _) => Some(Command::MoveLeft),
+                (false, true) => Some(Command::MoveRight),
+                (false, false) => None,
+            }
+        } else {
+            match target {
+                Some(target) if target < x && safe_left => Some(Command::MoveLeft),
+                Some(target) if target > x && safe_right => Some(Command::MoveRight),
+                _ => None,
+            }
+        };
+        if let Some(step) = step {
+            commands.push(step);
+            self.wait = MOVE_TICKS;
+        }
+        commands
+    }
+}
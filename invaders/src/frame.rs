@@ -1,13 +1,99 @@
-use crate::{NUM_COLS, NUM_ROWS};
+use crate::playfield::Playfield;
 
-pub type Frame = Vec<Vec<&'static str>>;
+// Terminal colors, kept independent of any terminal library
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Black,
+    DarkGrey,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub bold: bool,
+    pub dim: bool,
+    pub reverse: bool,
+}
 
-pub fn new_frame() -> Frame {
-    let mut cols = Vec::with_capacity(NUM_COLS);
-    for _ in 0..NUM_COLS {
-        let mut col = Vec::with_capacity(NUM_ROWS);
-        for _ in 0..NUM_ROWS {
-            col.push(" ")
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub attrs: Attributes,
+}
+
+impl Style {
+    pub const DEFAULT: Style = Style::fg(Color::White);
+
+    // The given foreground color on the black background
+    pub const fn fg(fg: Color) -> Self {
+        Self {
+            fg,
+            bg: Color::Black,
+            attrs: Attributes {
+                bold: false,
+                dim: false,
+                reverse: false,
+            },
+        }
+    }
+
+    pub const fn bold(mut self) -> Self {
+        self.attrs.bold = true;
+        self
+    }
+
+    pub const fn dim(mut self) -> Self {
+        self.attrs.dim = true;
+        self
+    }
+
+    pub const fn reverse(mut self) -> Self {
+        self.attrs.reverse = true;
+        self
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub glyph: char,
+    pub style: Style,
+}
+
+impl Cell {
+    pub const BLANK: Cell = Cell::new(' ', Style::DEFAULT);
+
+    pub const fn new(glyph: char, style: Style) -> Self {
+        Self { glyph, style }
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::BLANK
+    }
+}
+
+pub type Frame = Vec<Vec<Cell>>;
+
+pub fn new_frame(playfield: Playfield) -> Frame {
+    let mut cols = Vec::with_capacity(playfield.cols);
+    for _ in 0..playfield.cols {
+        let mut col = Vec::with_capacity(playfield.rows);
+        for _ in 0..playfield.rows {
+            col.push(Cell::BLANK)
         }
         cols.push(col);
     }
@@ -15,6 +101,106 @@ pub fn new_frame() -> Frame {
     cols
 }
 
+pub fn draw_text(frame: &mut Frame, x: usize, y: usize, text: &str, style: Style) {
+    for (i, c) in text.chars().enumerate() {
+        let Some(col) = frame.get_mut(x + i) else {
+            break;
+        };
+        if let Some(cell) = col.get_mut(y) {
+            *cell = Cell::new(c, style);
+        }
+    }
+}
+
+pub fn draw_text_centered(frame: &mut Frame, y: usize, text: &str, style: Style) {
+    let x = frame.len().saturating_sub(text.chars().count()) / 2;
+    draw_text(frame, x, y, text, style);
+}
+
+// Where something goes in the stack of a `Compositor`, from the bottom up
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    Background,
+    Bunkers,
+    Entities,
+    Effects,
+    Hud,
+    // Menus and messages over everything else
+    Overlay,
+}
+
 pub trait Drawable {
     fn draw(&self, frame: &mut Frame);
+
+    fn layer(&self) -> Layer {
+        Layer::Entities
+    }
+
+    // Draws at twice the vertical resolution, `pixels` has two rows for each row of the frame,
+    // see `squeeze`. Things that do not move smoothly fill both halves of their cells.
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        draw_doubled(pixels, |frame| self.draw(frame));
+    }
+}
+
+impl<T: Drawable + ?Sized> Drawable for &T {
+    fn draw(&self, frame: &mut Frame) {
+        (**self).draw(frame);
+    }
+
+    fn layer(&self) -> Layer {
+        (**self).layer()
+    }
+
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        (**self).draw_half_blocks(pixels);
+    }
+}
+
+const UPPER_HALF: char = '\u{2580}';
+const LOWER_HALF: char = '\u{2584}';
+
+// Draws at the normal resolution, then copies every cell that was drawn to both of its halves
+pub fn draw_doubled(pixels: &mut Frame, draw: impl FnOnce(&mut Frame)) {
+    let rows = pixels.first().map_or(0, |col| col.len()) / 2;
+    let mut frame = new_frame(Playfield {
+        cols: pixels.len(),
+        rows,
+    });
+    draw(&mut frame);
+    for (x, col) in frame.iter().enumerate() {
+        for (y, cell) in col.iter().enumerate() {
+            if *cell != Cell::BLANK {
+                pixels[x][2 * y] = *cell;
+                pixels[x][2 * y + 1] = *cell;
+            }
+        }
+    }
+}
+
+// Turns two rows of pixels into one row of the frame. Cells with the same halves keep their
+// glyph, the others become half blocks in the colors of their halves.
+pub fn squeeze(pixels: &Frame) -> Frame {
+    pixels
+        .iter()
+        .map(|col| {
+            col.chunks(2)
+                .map(|halves| {
+                    let top = halves[0];
+                    let bottom = halves.get(1).copied().unwrap_or(Cell::BLANK);
+                    if top == bottom {
+                        top
+                    } else if bottom == Cell::BLANK {
+                        Cell::new(UPPER_HALF, Style::fg(top.style.fg))
+                    } else if top == Cell::BLANK {
+                        Cell::new(LOWER_HALF, Style::fg(bottom.style.fg))
+                    } else {
+                        let mut style = Style::fg(top.style.fg);
+                        style.bg = bottom.style.fg;
+                        Cell::new(UPPER_HALF, style)
+                    }
+                })
+                .collect()
+        })
+        .collect()
 }
@@ -0,0 +1,520 @@
+use crate::bunkers::Bunkers;
+use crate::compositor::Compositor;
+use crate::frame::{self, Color, Drawable, Frame, Layer, Style};
+use crate::invaders::{InvaderKind, Invaders};
+use crate::levels::Levels;
+use crate::particles::Particles;
+use crate::player::Player;
+use crate::playfield::Playfield;
+use crate::powerups::{self, PowerUp, PowerUpKind};
+use crate::rng::{self, Rng};
+use crate::DEFAULT_LIVES;
+use rusty_time::Timer;
+use std::time::Duration;
+use std::vec::Drain;
+
+// Input understood by the game, independent of the keyboard or any other front-end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    MoveLeft,
+    MoveRight,
+    Shoot,
+}
+
+// Things that happened during a command or an update, for the front-end to react to (e.g. sounds)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+// Players are numbered from 0
+pub enum GameEvent {
+    ShotFired {
+        player: usize,
+    },
+    InvadersMoved,
+    // Hit without being killed, only happens to invaders taking several shots
+    InvaderDamaged {
+        player: usize,
+        kind: InvaderKind,
+    },
+    InvaderKilled {
+        player: usize,
+        kind: InvaderKind,
+        points: u32,
+    },
+    PlayerHit {
+        player: usize,
+    },
+    PowerUpCollected {
+        player: usize,
+        kind: PowerUpKind,
+    },
+    LevelCleared {
+        level: u32,
+    },
+    NextLevel {
+        level: u32,
+    },
+    Lose,
+}
+
+pub struct Settings {
+    // 1, or 2 for co-op
+    pub players: usize,
+    // Each player's
+    pub lives: u32,
+    // Seeds the invaders' firing pattern, the same seed and inputs give the same game
+    pub seed: u64,
+    pub levels: Levels,
+    pub playfield: Playfield,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            players: 1,
+            lives: DEFAULT_LIVES,
+            seed: rng::random_seed(),
+            levels: Levels::builtin(),
+            playfield: Playfield::default(),
+        }
+    }
+}
+
+pub struct Game {
+    // All share the same army, each with their own shots, lives and score
+    players: Vec<Player>,
+    invaders: Invaders,
+    bunkers: Bunkers,
+    // Dropped by killed invaders, falling towards the players
+    power_ups: Vec<PowerUp>,
+    levels: Levels,
+    playfield: Playfield,
+    level: u32,
+    // Pause between two levels, while it runs nothing moves
+    intermission: Option<Timer>,
+    rng: Rng,
+    // Debris of whatever was hit
+    particles: Particles,
+    events: Vec<GameEvent>,
+    over: bool,
+}
+
+const INTERMISSION: Duration = Duration::from_secs(3);
+// Mixed into the seed of the particles, which must not take numbers from the game's generator
+const PARTICLE_SEED: u64 = 0x5EED_0DEB_0215;
+pub const MAX_PLAYERS: usize = 2;
+// Game time advanced by each update of the front-end, 60 times per second
+pub const TICK: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+impl Game {
+    pub fn new(settings: Settings) -> Self {
+        let mut rng = Rng::new(settings.seed);
+        let players = settings.players.clamp(1, MAX_PLAYERS);
+        Self {
+            players: (0..players)
+                .map(|number| Player::numbered(number, players, settings.lives, settings.playfield))
+                .collect(),
+            invaders: Invaders::new(&settings.levels.get(1), settings.playfield, rng.next_u64()),
+            bunkers: Bunkers::new(settings.playfield),
+            power_ups: Vec::new(),
+            playfield: settings.playfield,
+            levels: settings.levels,
+            level: 1,
+            intermission: None,
+            rng,
+            particles: Particles::new(settings.playfield, settings.seed ^ PARTICLE_SEED),
+            events: Vec::new(),
+            over: false,
+        }
+    }
+
+    pub fn playfield(&self) -> Playfield {
+        self.playfield
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn invaders(&self) -> &Invaders {
+        &self.invaders
+    }
+
+    pub fn bunkers(&self) -> &Bunkers {
+        &self.bunkers
+    }
+
+    pub fn power_ups(&self) -> &[PowerUp] {
+        &self.power_ups
+    }
+
+    pub fn particles(&self) -> &Particles {
+        &self.particles
+    }
+
+    // Score of the whole team
+    pub fn score(&self) -> u32 {
+        self.players.iter().map(|player| player.score()).sum()
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn in_intermission(&self) -> bool {
+        self.intermission.is_some()
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    // Commands for players that are not in the game are ignored
+    pub fn command(&mut self, player: usize, command: Command) {
+        if self.over || self.in_intermission() {
+            return;
+        }
+        let Some(target) = self.players.get_mut(player) else {
+            return;
+        };
+
+        match command {
+            Command::MoveLeft => target.move_left(),
+            Command::MoveRight => target.move_right(),
+            Command::Shoot => {
+                if target.shoot() {
+                    self.events.push(GameEvent::ShotFired { player });
+                }
+            }
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        if self.over {
+            return;
+        }
+        // Debris settles during intermissions too
+        self.particles.update(delta);
+
+        if let Some(intermission) = self.intermission.as_mut() {
+            intermission.tick(delta);
+            if intermission.finished() {
+                self.intermission = None;
+                self.level += 1;
+                self.invaders = Invaders::new(
+                    &self.levels.get(self.level),
+                    self.playfield,
+                    self.rng.next_u64(),
+                );
+                self.events.push(GameEvent::NextLevel { level: self.level });
+            }
+            return;
+        }
+
+        for player in self.players.iter_mut() {
+            player.update(delta);
+        }
+        if self.invaders.update(delta) {
+            self.events.push(GameEvent::InvadersMoved);
+        }
+        self.bunkers.crush(&self.invaders);
+        self.bunkers.stop_shots(&mut self.invaders.bombs);
+
+        // Each player is credited for their own shots
+        for player in self.players.iter_mut() {
+            for hit in player.detect_hits(&mut self.invaders, &mut self.bunkers) {
+                if hit.killed {
+                    self.particles.burst(hit.x, hit.y, 6, hit.kind.style().fg);
+                }
+                if hit.killed && self.rng.below(powerups::DROP_CHANCE) == 0 {
+                    let kind = PowerUpKind::ALL[self.rng.below(PowerUpKind::ALL.len())];
+                    self.power_ups
+                        .push(PowerUp::new(hit.x, hit.y, kind, self.playfield));
+                }
+                self.events.push(if hit.killed {
+                    GameEvent::InvaderKilled {
+                        player: player.number(),
+                        kind: hit.kind,
+                        points: hit.points,
+                    }
+                } else {
+                    GameEvent::InvaderDamaged {
+                        player: player.number(),
+                        kind: hit.kind,
+                    }
+                });
+            }
+        }
+
+        for power_up in self.power_ups.iter_mut() {
+            power_up.update(delta);
+        }
+        for player in self.players.iter_mut() {
+            if player.dead() {
+                continue;
+            }
+            let bounds = player.bounds();
+            if let Some(idx) = self
+                .power_ups
+                .iter()
+                .position(|power_up| bounds.contains(power_up.x, power_up.y))
+            {
+                let kind = self.power_ups.remove(idx).kind;
+                player.collect(kind);
+                self.events.push(GameEvent::PowerUpCollected {
+                    player: player.number(),
+                    kind,
+                });
+            }
+        }
+        self.power_ups.retain(|power_up| !power_up.dead());
+
+        for (x, y) in self.bunkers.drain_hits() {
+            self.particles.burst(x, y, 3, Color::Green);
+        }
+
+        for player in self.players.iter_mut() {
+            if player.detect_bombs(&mut self.invaders) {
+                self.particles
+                    .burst(player.x(), player.y(), 12, Color::Yellow);
+                self.events.push(GameEvent::PlayerHit {
+                    player: player.number(),
+                });
+            }
+        }
+
+        // Next level or lose?
+        if self.invaders.all_killed() {
+            self.events
+                .push(GameEvent::LevelCleared { level: self.level });
+            self.intermission = Some(Timer::new(INTERMISSION));
+            self.power_ups.clear();
+        } else if self.invaders.reach_bottom() || self.players.iter().all(|player| player.dead()) {
+            self.events.push(GameEvent::Lose);
+            self.over = true;
+        }
+    }
+
+    // Events produced since the last call, oldest first
+    pub fn drain_events(&mut self) -> Drain<'_, GameEvent> {
+        self.events.drain(..)
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new(Settings::default())
+    }
+}
+
+impl Game {
+    // Everything on the playfield, each part on its own layer
+    pub fn add_layers<'a>(&'a self, compositor: &mut Compositor<'a>) {
+        compositor.add(&self.bunkers);
+        for power_up in self.power_ups.iter() {
+            compositor.add(power_up);
+        }
+        for player in self.players.iter() {
+            compositor.add(player);
+        }
+        compositor.add(&self.invaders);
+        compositor.add(&self.particles);
+        if self.intermission.is_some() {
+            compositor.add(LevelBanner { level: self.level });
+        }
+    }
+}
+
+impl Drawable for Game {
+    fn draw(&self, frame: &mut Frame) {
+        let mut compositor = Compositor::new();
+        self.add_layers(&mut compositor);
+        compositor.draw(frame);
+    }
+}
+
+// Shown during the intermission after a level
+struct LevelBanner {
+    level: u32,
+}
+
+impl Drawable for LevelBanner {
+    fn draw(&self, frame: &mut Frame) {
+        let style = Style::fg(Color::Yellow).bold();
+        let cleared = format!("LEVEL {} CLEARED", self.level);
+        let next = format!("GET READY FOR LEVEL {}", self.level + 1);
+        frame::draw_text_centered(frame, 8, &cleared, style);
+        frame::draw_text_centered(frame, 10, &next, style);
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Overlay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(levels: Levels) -> Game {
+        Game::new(Settings {
+            seed: 42,
+            levels,
+            ..Settings::default()
+        })
+    }
+
+    // Updates until the predicate holds for an event, returning every event up to that one
+    fn run_until(
+        game: &mut Game,
+        max_ticks: u32,
+        done: impl Fn(&GameEvent) -> bool,
+    ) -> Vec<GameEvent> {
+        let mut events = Vec::new();
+        for _ in 0..max_ticks {
+            game.update(TICK);
+            for event in game.drain_events() {
+                events.push(event);
+                if done(&event) {
+                    return events;
+                }
+            }
+        }
+        panic!("no matching event in {} ticks: {:?}", max_ticks, events);
+    }
+
+    // The single invader of these waves is right above the player once moved one column left
+    fn aim(game: &mut Game) {
+        game.command(0, Command::MoveLeft);
+        let invader = &game.invaders().army[0];
+        assert_eq!(game.players()[0].x(), invader.x);
+    }
+
+    #[test]
+    fn shooting_an_invader_scores_its_points() {
+        let mut game = game(Levels::single("A", 2000, 1, 1_000_000));
+        aim(&mut game);
+        game.command(0, Command::Shoot);
+        assert_eq!(
+            game.drain_events().collect::<Vec<_>>(),
+            [GameEvent::ShotFired { player: 0 }]
+        );
+
+        let events = run_until(&mut game, 120, |event| {
+            matches!(event, GameEvent::InvaderKilled { .. })
+        });
+        assert_eq!(
+            events.last(),
+            Some(&GameEvent::InvaderKilled {
+                player: 0,
+                kind: InvaderKind::Squid,
+                points: 30,
+            })
+        );
+        assert_eq!(game.score(), 30);
+        assert_eq!(game.players()[0].score(), 30);
+    }
+
+    #[test]
+    fn a_piercing_shot_hits_each_invader_once() {
+        // A tank takes two hits, a single piercing shot only damages it
+        let mut game = game(Levels::single("D", 2000, 1, 1_000_000));
+        game.players[0].collect(PowerUpKind::Piercing);
+        aim(&mut game);
+        game.command(0, Command::Shoot);
+        let events = run_until(&mut game, 120, |event| {
+            matches!(event, GameEvent::InvaderDamaged { .. })
+        });
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::InvaderKilled { .. })));
+
+        // Long enough for the shot to leave the screen
+        for _ in 0..120 {
+            game.update(TICK);
+        }
+        assert!(!game.drain_events().any(|event| matches!(
+            event,
+            GameEvent::InvaderDamaged { .. } | GameEvent::InvaderKilled { .. }
+        )));
+        assert_eq!(game.invaders().army[0].health, 1);
+    }
+
+    #[test]
+    fn a_bomb_landing_costs_a_life() {
+        let mut game = game(Levels::single("A", 2000, 1, 1));
+        aim(&mut game);
+        run_until(&mut game, 600, |event| {
+            matches!(event, GameEvent::PlayerHit { player: 0 })
+        });
+        assert_eq!(game.players()[0].lives(), DEFAULT_LIVES - 1);
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn clearing_a_level_starts_the_next_after_the_intermission() {
+        let mut game = game(Levels::single("A", 2000, 1, 1_000_000));
+        aim(&mut game);
+        game.command(0, Command::Shoot);
+        run_until(&mut game, 120, |event| {
+            matches!(event, GameEvent::LevelCleared { level: 1 })
+        });
+        assert!(game.in_intermission());
+
+        // Nothing happens before the intermission is over
+        let ticks = (INTERMISSION.as_secs_f64() / TICK.as_secs_f64()) as u32;
+        let events = run_until(&mut game, ticks + 2, |event| {
+            matches!(event, GameEvent::NextLevel { .. })
+        });
+        assert_eq!(events, [GameEvent::NextLevel { level: 2 }]);
+        assert!(!game.in_intermission());
+        assert_eq!(game.level(), 2);
+        assert!(!game.invaders().all_killed());
+    }
+
+    #[test]
+    fn the_army_reaching_the_bottom_loses() {
+        // Dropping to the bottom the first time the army reaches a side
+        let mut game = game(Levels::single("A", 2000, 20, 1_000_000));
+        let ticks = 60 * 60;
+        let events = run_until(&mut game, ticks, |event| *event == GameEvent::Lose);
+        assert!(!events.contains(&GameEvent::PlayerHit { player: 0 }));
+        assert!(game.is_over());
+        assert_eq!(game.players()[0].lives(), DEFAULT_LIVES);
+    }
+
+    #[test]
+    fn the_fastest_army_keeps_its_pace_after_dropping() {
+        let mut game = game(Levels::single("A", 250, 1, 1_000_000));
+        let top = game.invaders().army[0].y;
+        // A minute of game time, a step every 250 ms is enough to reach a side several times
+        for _ in 0..60 * 60 {
+            game.update(TICK);
+        }
+        assert!(game.invaders().army[0].y > top + 2);
+        assert!(!game.is_over());
+    }
+
+    #[test]
+    fn the_same_seed_and_commands_give_the_same_game() {
+        let play = || {
+            let mut game = game(Levels::builtin());
+            let mut events = Vec::new();
+            for tick in 0..3000u32 {
+                let command = match tick % 7 {
+                    0 | 3 => Some(Command::Shoot),
+                    1 if tick % 400 < 200 => Some(Command::MoveLeft),
+                    1 => Some(Command::MoveRight),
+                    _ => None,
+                };
+                if let Some(command) = command {
+                    game.command(0, command);
+                }
+                game.update(TICK);
+                events.extend(game.drain_events());
+            }
+            (events, game.score(), game.players()[0].lives())
+        };
+        let first = play();
+        assert!(first.0.len() > 100);
+        assert_eq!(first, play());
+    }
+}
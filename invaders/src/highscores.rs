@@ -0,0 +1,256 @@
+use crate::frame::{self, Color, Drawable, Frame, Layer, Style};
+use crate::paths;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// First line of the file, bumped whenever the format changes
+const HEADER: &str = "invaders-highscores v1";
+const FILE_NAME: &str = "highscores.txt";
+pub const MAX_ENTRIES: usize = 10;
+pub const MAX_NAME_LEN: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+}
+
+// Best scores first
+#[derive(Default)]
+pub struct HighScores {
+    entries: Vec<HighScore>,
+}
+
+impl HighScores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        paths::data_dir().map(|dir| dir.join(FILE_NAME))
+    }
+
+    // A missing or unreadable file is an empty table, not an error
+    pub fn load(path: &Path) -> Self {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::new(),
+        }
+    }
+
+    // Unknown versions give an empty table, corrupt lines only lose their own entry
+    pub fn parse(text: &str) -> Self {
+        let mut table = Self::new();
+        let mut lines = text.lines();
+        if lines.next().map(str::trim) != Some(HEADER) {
+            return table;
+        }
+
+        for line in lines {
+            if let Some((score, name)) = line.trim().split_once(' ') {
+                if let Ok(score) = score.parse() {
+                    if valid_name(name) {
+                        table.insert(name, score);
+                    }
+                }
+            }
+        }
+        table
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write then rename, so a crash half way cannot corrupt the previous table
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, self.to_string())?;
+        fs::rename(tmp, path)
+    }
+
+    pub fn top(&self) -> u32 {
+        self.entries.first().map(|entry| entry.score).unwrap_or(0)
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < MAX_ENTRIES
+                || self.entries.last().is_some_and(|entry| score > entry.score))
+    }
+
+    // Returns the rank of the new entry, if it made it into the table
+    pub fn insert(&mut self, name: &str, score: u32) -> Option<usize> {
+        if !self.qualifies(score) {
+            return None;
+        }
+
+        // Ties go below the existing entries
+        let rank = self
+            .entries
+            .iter()
+            .position(|entry| score > entry.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(
+            rank,
+            HighScore {
+                name: name.to_string(),
+                score,
+            },
+        );
+        self.entries.truncate(MAX_ENTRIES);
+        Some(rank)
+    }
+}
+
+impl Display for HighScores {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        for entry in self.entries.iter() {
+            writeln!(f, "{} {}", entry.score, entry.name)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drawable for HighScores {
+    fn draw(&self, frame: &mut Frame) {
+        frame::draw_text_centered(frame, 3, "HIGH SCORES", Style::fg(Color::Yellow).bold());
+        for (rank, entry) in self.entries.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:<width$} {:06}",
+                rank + 1,
+                entry.name,
+                entry.score,
+                width = MAX_NAME_LEN
+            );
+            frame::draw_text_centered(frame, 5 + rank, &line, Style::DEFAULT);
+        }
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Overlay
+    }
+}
+
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// Name typed by the player at game over
+pub struct NameEntry {
+    pub name: String,
+    pub score: u32,
+}
+
+impl NameEntry {
+    pub fn new(score: u32) -> Self {
+        Self {
+            name: String::new(),
+            score,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        if c.is_ascii_alphanumeric() && self.name.len() < MAX_NAME_LEN {
+            self.name.push(c.to_ascii_uppercase());
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.name.pop();
+    }
+
+    pub fn done(&self) -> bool {
+        valid_name(&self.name)
+    }
+}
+
+impl Drawable for NameEntry {
+    fn draw(&self, frame: &mut Frame) {
+        let name = format!("NAME {:_<width$}", self.name, width = MAX_NAME_LEN);
+        let score = format!("SCORE {:06}", self.score);
+        frame::draw_text_centered(frame, 6, "NEW HIGH SCORE!", Style::fg(Color::Yellow).bold());
+        frame::draw_text_centered(frame, 8, &score, Style::DEFAULT);
+        frame::draw_text_centered(frame, 10, &name, Style::fg(Color::Green).bold());
+        frame::draw_text_centered(
+            frame,
+            12,
+            "ENTER TO SAVE, ESC TO SKIP",
+            Style::DEFAULT.dim(),
+        );
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Overlay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(table: &HighScores) -> Vec<(&str, u32)> {
+        table
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.score))
+            .collect()
+    }
+
+    #[test]
+    fn other_versions_give_an_empty_table() {
+        assert!(names(&HighScores::parse("")).is_empty());
+        let text = "invaders-highscores v0\n100 ACE\n";
+        assert!(names(&HighScores::parse(text)).is_empty());
+    }
+
+    #[test]
+    fn corrupt_lines_only_lose_their_own_entry() {
+        let text = format!(
+            "{}\n100 ACE\nlots BOB\n200\n300 NOT-OK\n400 WAYTOOLONG\n\n50 CAT\n",
+            HEADER
+        );
+        assert_eq!(
+            names(&HighScores::parse(&text)),
+            [("ACE", 100), ("CAT", 50)]
+        );
+    }
+
+    #[test]
+    fn only_the_best_fit_in_the_table() {
+        let mut text = format!("{}\n", HEADER);
+        for score in 1..=MAX_ENTRIES as u32 + 2 {
+            text += &format!("{} P{}\n", score * 10, score);
+        }
+        let table = HighScores::parse(&text);
+        assert_eq!(table.entries.len(), MAX_ENTRIES);
+        assert_eq!(table.top(), (MAX_ENTRIES as u32 + 2) * 10);
+        assert_eq!(table.entries.last().unwrap().score, 30);
+        assert!(!table.qualifies(30));
+        assert!(table.qualifies(31));
+    }
+
+    #[test]
+    fn ties_rank_below_existing_entries() {
+        let mut table = HighScores::new();
+        assert_eq!(table.insert("ACE", 100), Some(0));
+        assert_eq!(table.insert("BOB", 100), Some(1));
+        assert_eq!(table.insert("CAT", 150), Some(0));
+        assert_eq!(names(&table), [("CAT", 150), ("ACE", 100), ("BOB", 100)]);
+        assert_eq!(table.insert("NIL", 0), None);
+    }
+
+    #[test]
+    fn the_written_table_reads_back_the_same() {
+        let mut table = HighScores::new();
+        table.insert("ACE", 100);
+        table.insert("BOB", 300);
+        table.insert("CAT", 100);
+        let read = HighScores::parse(&table.to_string());
+        assert_eq!(read.entries, table.entries);
+    }
+}
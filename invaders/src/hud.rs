@@ -0,0 +1,153 @@
+use crate::frame::{self, Color, Drawable, Frame, Layer, Style};
+use crate::game::Game;
+use crate::powerups::PowerUpKind;
+use crate::HUD_ROW;
+
+pub struct Hud {
+    pub score: u32,
+    pub high_score: u32,
+    pub level: u32,
+    pub lives: u32,
+    // Score and lives of each player, only shown in co-op
+    pub players: Vec<(u32, u32)>,
+}
+
+impl Hud {
+    pub fn new(game: &Game, high_score: u32) -> Self {
+        let players: Vec<(u32, u32)> = game
+            .players()
+            .iter()
+            .map(|player| (player.score(), player.lives()))
+            .collect();
+        Self {
+            score: game.score(),
+            // The current game counts as soon as it beats the table
+            high_score: high_score.max(game.score()),
+            level: game.level(),
+            lives: players[0].1,
+            players: if players.len() > 1 {
+                players
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    // No room for the high score: each player on their side, the level in the middle
+    fn draw_co_op(&self, frame: &mut Frame) {
+        let style = Style::fg(Color::White);
+        for (number, &(score, lives)) in self.players.iter().enumerate() {
+            let score_text = format!("{}P {:06} ", number + 1, score);
+            let lives_text = format!("L{}", lives);
+            let x = if number == 0 {
+                0
+            } else {
+                frame.len() - score_text.len() - lives_text.len()
+            };
+            frame::draw_text(frame, x, HUD_ROW, &score_text, style);
+            let lives_x = x + score_text.len();
+            frame::draw_text(frame, lives_x, HUD_ROW, &lives_text, lives_style(lives));
+        }
+        let level = format!("LV {}", self.level);
+        frame::draw_text_centered(frame, HUD_ROW, &level, style);
+    }
+}
+
+fn lives_style(lives: u32) -> Style {
+    if lives > 1 {
+        Style::fg(Color::White)
+    } else {
+        // Last life
+        Style::fg(Color::Red).bold()
+    }
+}
+
+impl Drawable for Hud {
+    fn draw(&self, frame: &mut Frame) {
+        if !self.players.is_empty() {
+            self.draw_co_op(frame);
+            return;
+        }
+
+        let score = format!("SCORE {:06}", self.score);
+        let high_score = format!("HI {:06}", self.high_score);
+        let level = format!("LV {}", self.level);
+        let lives = format!("LIVES {}", self.lives);
+
+        // Spread evenly over the row, the lives against the right side
+        let style = Style::fg(Color::White);
+        let width = score.len() + high_score.len() + level.len() + lives.len();
+        let gap = frame.len().saturating_sub(width) / 3;
+        let mut x = 0;
+        for text in [&score, &high_score, &level] {
+            frame::draw_text(frame, x, HUD_ROW, text, style);
+            x += text.len() + gap;
+        }
+        frame::draw_text(
+            frame,
+            frame.len().saturating_sub(lives.len()),
+            HUD_ROW,
+            &lives,
+            lives_style(self.lives),
+        );
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Hud
+    }
+}
+
+// Running power-ups of each player with their whole seconds left, on the row under the game
+pub struct EffectTimers {
+    pub effects: Vec<Vec<(PowerUpKind, u64)>>,
+}
+
+impl EffectTimers {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            effects: game
+                .players()
+                .iter()
+                .map(|player| {
+                    player
+                        .effects()
+                        .iter()
+                        .map(|effect| (effect.kind, effect.remaining().as_secs() + 1))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Drawable for EffectTimers {
+    // The first player's on the left and the second's on the right, each cut to their share of
+    // the width
+    fn draw(&self, frame: &mut Frame) {
+        let Some(row) = frame.first().map(|col| col.len().saturating_sub(1)) else {
+            return;
+        };
+        let width = frame.len() / self.effects.len().max(1);
+        let style = Style::fg(Color::Magenta).bold();
+        for (number, effects) in self.effects.iter().enumerate() {
+            let text: String = effects
+                .iter()
+                .map(|(kind, secs)| format!("{} {}", kind.name(), secs))
+                .collect::<Vec<_>>()
+                .join(" ")
+                .chars()
+                .take(width.saturating_sub(1))
+                .collect();
+            let x = if number == 0 {
+                0
+            } else {
+                frame.len().saturating_sub(text.len())
+            };
+            frame::draw_text(frame, x, row, &text, style);
+        }
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Hud
+    }
+}
@@ -1,52 +1,233 @@
-use crate::frame::{Drawable, Frame};
-use crate::{NUM_COLS, NUM_ROWS};
+use crate::animation::{Animation, Repeat};
+use crate::frame::{self, Color, Drawable, Frame, Style};
+use crate::levels::{self, Level, Levels};
+use crate::playfield::Playfield;
+use crate::rng::{self, Rng};
+use crate::saucer::{Saucer, SAUCER_POINTS};
+use crate::shot::{Direction, Shot};
+use crate::sprite::{Rect, SpatialGrid, Sprite};
 use rusty_time::Timer;
-use std::cmp::max;
+use std::collections::BTreeMap;
 use std::time::Duration;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvaderKind {
+    Squid,
+    Crab,
+    Octopus,
+    // Takes two shots
+    Tank,
+    // The mystery ship, never part of the army
+    Saucer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvaderSize {
+    // One cell
+    Small,
+    // Three columns by two rows
+    Large,
+}
+
+impl InvaderKind {
+    // Character selecting the kind in level files, lower case for the large ones
+    pub fn from_char(c: char) -> Option<(Self, InvaderSize)> {
+        let size = if c.is_ascii_lowercase() {
+            InvaderSize::Large
+        } else {
+            InvaderSize::Small
+        };
+        let kind = match c.to_ascii_uppercase() {
+            'A' => InvaderKind::Squid,
+            'B' => InvaderKind::Crab,
+            'C' => InvaderKind::Octopus,
+            'D' => InvaderKind::Tank,
+            _ => return None,
+        };
+        Some((kind, size))
+    }
+
+    pub fn health(self) -> u32 {
+        match self {
+            InvaderKind::Tank => 2,
+            _ => 1,
+        }
+    }
+
+    // The saucer is worth a random bonus instead, see `SAUCER_POINTS`
+    pub fn points(self) -> u32 {
+        match self {
+            InvaderKind::Squid => 30,
+            InvaderKind::Crab => 20,
+            InvaderKind::Octopus => 10,
+            InvaderKind::Tank => 40,
+            InvaderKind::Saucer => 0,
+        }
+    }
+
+    // Relative chance of being the one to drop the next bomb
+    pub fn fire_weight(self) -> usize {
+        match self {
+            InvaderKind::Squid => 3,
+            InvaderKind::Crab => 2,
+            InvaderKind::Octopus => 1,
+            InvaderKind::Tank => 4,
+            InvaderKind::Saucer => 0,
+        }
+    }
+
+    // Two sprites per kind and size, alternated as the army moves or the saucer flies
+    pub fn sprites(self, size: InvaderSize) -> [Sprite; 2] {
+        match (size, self) {
+            (InvaderSize::Small, InvaderKind::Squid) => [Sprite::new(&["Y"]), Sprite::new(&["y"])],
+            (InvaderSize::Small, InvaderKind::Crab) => [Sprite::new(&["x"]), Sprite::new(&["+"])],
+            (InvaderSize::Small, InvaderKind::Octopus) => {
+                [Sprite::new(&["M"]), Sprite::new(&["W"])]
+            }
+            (InvaderSize::Small, InvaderKind::Tank) => [Sprite::new(&["H"]), Sprite::new(&["#"])],
+            // The saucer only comes in one size
+            (_, InvaderKind::Saucer) => [Sprite::new(&["@"]), Sprite::new(&["O"])],
+            (InvaderSize::Large, InvaderKind::Squid) => [
+                Sprite::new(&["/o\\", "/ \\"]),
+                Sprite::new(&["/o\\", "\\ /"]),
+            ],
+            (InvaderSize::Large, InvaderKind::Crab) => {
+                [Sprite::new(&["{x}", "/ \\"]), Sprite::new(&["{x}", "| |"])]
+            }
+            (InvaderSize::Large, InvaderKind::Octopus) => {
+                [Sprite::new(&["(M)", "/ \\"]), Sprite::new(&["(W)", "\\ /"])]
+            }
+            (InvaderSize::Large, InvaderKind::Tank) => {
+                [Sprite::new(&["[H]", "/=\\"]), Sprite::new(&["[#]", "\\=/"])]
+            }
+        }
+    }
+
+    pub(crate) fn style(self) -> Style {
+        match self {
+            InvaderKind::Squid => Style::fg(Color::Magenta),
+            InvaderKind::Crab => Style::fg(Color::Cyan),
+            InvaderKind::Octopus => Style::fg(Color::Yellow),
+            InvaderKind::Tank => Style::fg(Color::Red).bold(),
+            InvaderKind::Saucer => Style::fg(Color::Red).bold().reverse(),
+        }
+    }
+}
+
+// Positioned by the top left corner of its sprite
 pub struct Invader {
+    // Its place in the wave, kept while the army moves and loses invaders
+    pub id: usize,
     pub x: usize,
     pub y: usize,
+    pub kind: InvaderKind,
+    pub size: InvaderSize,
+    pub health: u32,
+}
+
+impl Invader {
+    // Both sprites of a kind have the same size
+    pub fn bounds(&self) -> Rect {
+        self.kind.sprites(self.size)[0].bounds(self.x, self.y)
+    }
+}
+
+// What a shot did to the invader it hit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvaderHit {
+    pub kind: InvaderKind,
+    // Where the shot hit
+    pub x: usize,
+    pub y: usize,
+    pub killed: bool,
+    // Zero unless killed
+    pub points: u32,
 }
 
 pub struct Invaders {
     pub army: Vec<Invader>,
+    pub bombs: Vec<Shot>,
+    pub saucer: Option<Saucer>,
     move_timer: Timer,
+    // Index of the sprite shown, half of each step on either
+    march: Animation<usize>,
+    fire_timer: Timer,
+    saucer_timer: Timer,
     // Positive move to right, negative move to left
     direction: i32,
+    // Rows moved down when reaching a side
+    drop: usize,
+    playfield: Playfield,
+    rng: Rng,
+    // Index in the army of the invader on each cell
+    grid: SpatialGrid,
 }
 
+// Taken off the time between two steps each time the army moves down
+const SPEEDUP_PER_DROP: Duration = Duration::from_millis(250);
+// How often a saucer may show up
+const SAUCER_INTERVAL: Duration = Duration::from_secs(20);
+
 impl Invaders {
-    pub fn new() -> Self {
-        let mut army = Vec::new();
-
-        for x in 0..NUM_COLS {
-            for y in 0..NUM_ROWS {
-                // Not on the X edges
-                if (x > 1) && (x < NUM_COLS - 2)
-                    // Not on first row
-                    && (y > 0)
-                    // 4 rows of invaders
-                    && (y < 9)
-                    // Only on even cols
-                    && (x % 2 == 0)
-                    // Only on even rows
-                    && (y % 2 == 0)
-                {
-                    army.push(Invader { x, y })
-                }
-            }
-        }
+    pub fn new(level: &Level, playfield: Playfield, seed: u64) -> Self {
+        let left = level.left(playfield);
+        let army = level
+            .wave
+            .iter()
+            .enumerate()
+            .map(|(id, placement)| Invader {
+                id,
+                x: left + placement.x,
+                y: level.top + placement.y,
+                kind: placement.kind,
+                size: placement.size,
+                health: placement.kind.health(),
+            })
+            .collect();
 
-        Self {
+        let mut invaders = Self {
             army,
-            move_timer: Timer::new(Duration::from_millis(2000)),
+            bombs: Vec::new(),
+            saucer: None,
+            move_timer: Timer::new(level.move_interval),
+            march: march(level.move_interval),
+            fire_timer: Timer::new(level.fire_interval),
+            saucer_timer: Timer::new(SAUCER_INTERVAL),
             direction: 1,
+            drop: level.drop,
+            playfield,
+            rng: Rng::new(seed),
+            grid: SpatialGrid::new(playfield),
+        };
+        invaders.index();
+        invaders
+    }
+
+    // The sprites of a kind and size all have the same cells, so the first one does for hits
+    fn index(&mut self) {
+        self.grid.clear();
+        for (idx, invader) in self.army.iter().enumerate() {
+            let sprite = invader.kind.sprites(invader.size)[0];
+            self.grid.insert(idx, invader.x, invader.y, &sprite);
         }
     }
 
+    // Whether some invader of the army covers the given cell
+    pub fn covers(&self, x: usize, y: usize) -> bool {
+        self.grid.get(x, y).is_some()
+    }
+
+    // Id of the invader of the army covering the given cell
+    pub fn invader_at(&self, x: usize, y: usize) -> Option<usize> {
+        self.grid.get(x, y).map(|idx| self.army[idx].id)
+    }
+
     pub fn update(&mut self, delta: Duration) -> bool {
+        self.update_bombs(delta);
+        self.update_saucer(delta);
+
         self.move_timer.tick(delta);
+        self.march.update(delta);
         if self.move_timer.finished() {
             self.move_timer.reset();
             let mut downwards = false;
@@ -59,64 +240,230 @@ impl Invaders {
                     downwards = true;
                 }
             } else {
-                let max_x = self.army.iter().map(|invader| invader.x).max().unwrap_or(0);
+                let max_x = self
+                    .army
+                    .iter()
+                    .map(|invader| invader.x + invader.bounds().width - 1)
+                    .max()
+                    .unwrap_or(0);
                 // Move left and downwards if reached right margin
-                if max_x == NUM_COLS - 1 {
+                if max_x == self.playfield.cols - 1 {
                     self.direction = -1;
                     downwards = true;
                 }
             }
 
             if downwards {
-                let new_duration = max(self.move_timer.duration().as_millis() - 250, 250);
-                self.move_timer = Timer::new(Duration::from_millis(new_duration as u64));
+                let faster = self
+                    .move_timer
+                    .duration()
+                    .saturating_sub(SPEEDUP_PER_DROP)
+                    .max(levels::MIN_MOVE_INTERVAL);
+                self.move_timer = Timer::new(faster);
+                self.march = march(self.move_timer.duration());
                 for invader in self.army.iter_mut() {
-                    invader.y += 1;
+                    let lowest = self.playfield.bottom() + 1 - invader.bounds().height;
+                    invader.y = (invader.y + self.drop).min(lowest);
                 }
             } else {
                 for invader in self.army.iter_mut() {
                     invader.x = ((invader.x as i32) + self.direction) as usize;
                 }
             }
+            self.index();
 
             return true;
         }
         false
     }
 
-    pub fn all_killed(&self) -> bool {
-        self.army.is_empty()
+    fn update_bombs(&mut self, delta: Duration) {
+        // Bombs are removed one update late, so the ones reaching the bottom row can still hit
+        self.bombs.retain(|bomb| !bomb.dead());
+        for bomb in self.bombs.iter_mut() {
+            bomb.update(delta);
+        }
+
+        self.fire_timer.tick(delta);
+        if self.fire_timer.finished() {
+            self.fire_timer.reset();
+            let shooters = self.shooters();
+            let total: usize = shooters
+                .iter()
+                .map(|idx| self.army[*idx].kind.fire_weight())
+                .sum();
+            if total > 0 {
+                // Weighted pick, the more trigger-happy kinds fire more often
+                let mut pick = self.rng.below(total);
+                for idx in shooters {
+                    let invader = &self.army[idx];
+                    let weight = invader.kind.fire_weight();
+                    if pick < weight {
+                        // From under the middle of the sprite
+                        let bounds = invader.bounds();
+                        if bounds.y + bounds.height <= self.playfield.bottom() {
+                            self.bombs.push(Shot::new(
+                                bounds.x + bounds.width / 2,
+                                bounds.y + bounds.height,
+                                Direction::Down,
+                                self.playfield,
+                            ));
+                        }
+                        break;
+                    }
+                    pick -= weight;
+                }
+            }
+        }
     }
 
-    pub fn reach_bottom(&self) -> bool {
-        self.army.iter().map(|invader| invader.y).max().unwrap_or(0) >= NUM_ROWS - 1
+    // Indices of the bottom invader of each column, the only ones with a clear line of fire
+    fn shooters(&self) -> Vec<usize> {
+        let mut bottom: BTreeMap<usize, usize> = BTreeMap::new();
+        for (idx, invader) in self.army.iter().enumerate() {
+            let lowest = bottom.entry(invader.x).or_insert(idx);
+            let bottom_row = |invader: &Invader| invader.y + invader.bounds().height;
+            if bottom_row(invader) > bottom_row(&self.army[*lowest]) {
+                *lowest = idx;
+            }
+        }
+        bottom.into_values().collect()
     }
 
-    pub fn kill_invader_at(&mut self, x: usize, y: usize) -> bool {
-        if let Some(idx) = self
-            .army
-            .iter()
-            .position(|invader| (invader.x == x) && (invader.y == y))
+    fn update_saucer(&mut self, delta: Duration) {
+        if let Some(saucer) = self.saucer.as_mut() {
+            saucer.update(delta);
+            if saucer.gone() {
+                self.saucer = None;
+            }
+            return;
+        }
+
+        // Every interval there is a one in two chance for a saucer to show up
+        self.saucer_timer.tick(delta);
+        if self.saucer_timer.finished() {
+            self.saucer_timer.reset();
+            if self.rng.below(2) == 0 {
+                let from_left = self.rng.below(2) == 0;
+                let points = SAUCER_POINTS[self.rng.below(SAUCER_POINTS.len())];
+                self.saucer = Some(Saucer::new(from_left, points, self.playfield));
+            }
+        }
+    }
+
+    // Explodes a live bomb at the given position, returning whether there was one
+    pub fn bomb_at(&mut self, x: usize, y: usize) -> bool {
+        if let Some(bomb) = self
+            .bombs
+            .iter_mut()
+            .find(|bomb| !bomb.exploding && (bomb.x == x) && (bomb.y == y))
         {
-            self.army.remove(idx);
+            bomb.explode();
             true
         } else {
             false
         }
     }
+
+    pub fn all_killed(&self) -> bool {
+        self.army.is_empty()
+    }
+
+    pub fn reach_bottom(&self) -> bool {
+        self.army
+            .iter()
+            .map(|invader| invader.y + invader.bounds().height - 1)
+            .max()
+            .unwrap_or(0)
+            >= self.playfield.bottom()
+    }
+
+    // Damages the invader or saucer at the given position, if any, and reports what was hit
+    pub fn kill_invader_at(&mut self, x: usize, y: usize) -> Option<InvaderHit> {
+        if let Some(saucer) = self
+            .saucer
+            .take_if(|saucer| (saucer.x == x) && (saucer.y == y))
+        {
+            return Some(InvaderHit {
+                kind: InvaderKind::Saucer,
+                x,
+                y,
+                killed: true,
+                points: saucer.points,
+            });
+        }
+
+        let idx = self.grid.get(x, y)?;
+        let invader = &mut self.army[idx];
+        let kind = invader.kind;
+        invader.health -= 1;
+        if invader.health > 0 {
+            return Some(InvaderHit {
+                kind,
+                x,
+                y,
+                killed: false,
+                points: 0,
+            });
+        }
+
+        self.army.remove(idx);
+        self.index();
+        Some(InvaderHit {
+            kind,
+            x,
+            y,
+            killed: true,
+            points: kind.points(),
+        })
+    }
 }
 
-impl Drawable for Invaders {
-    fn draw(&self, frame: &mut Frame) {
+impl Default for Invaders {
+    fn default() -> Self {
+        Self::new(
+            &Levels::builtin().get(1),
+            Playfield::default(),
+            rng::random_seed(),
+        )
+    }
+}
+
+fn march(step: Duration) -> Animation<usize> {
+    Animation::even(&[0, 1], step / 2, Repeat::Loop)
+}
+
+impl Invaders {
+    fn draw_army(&self, frame: &mut Frame) {
+        let sprite = self.march.current();
         for invader in self.army.iter() {
-            frame[invader.x][invader.y] = if (self.move_timer.remaining().as_secs_f32()
-                / self.move_timer.duration().as_secs_f32())
-                > 0.5
-            {
-                "x"
-            } else {
-                "+"
+            let mut style = invader.kind.style();
+            // Damaged invaders fade
+            if invader.health < invader.kind.health() {
+                style.attrs.bold = false;
+                style = style.dim();
             }
+            invader.kind.sprites(invader.size)[sprite].draw(frame, invader.x, invader.y, style);
+        }
+
+        if let Some(saucer) = self.saucer.as_ref() {
+            saucer.draw(frame);
+        }
+    }
+}
+
+impl Drawable for Invaders {
+    fn draw(&self, frame: &mut Frame) {
+        self.draw_army(frame);
+        for bomb in self.bombs.iter() {
+            bomb.draw(frame);
+        }
+    }
+
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        frame::draw_doubled(pixels, |frame| self.draw_army(frame));
+        for bomb in self.bombs.iter() {
+            bomb.draw_half_blocks(pixels);
         }
     }
 }
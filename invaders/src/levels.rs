@@ -0,0 +1,553 @@
+use crate::invaders::{InvaderKind, InvaderSize};
+use crate::playfield::Playfield;
+use crate::sprite::Rect;
+use crate::SAUCER_ROW;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::time::Duration;
+
+// Levels shipped with the game, see the file itself for the format
+const BUILTIN: &str = include_str!("../levels/default.txt");
+// Waves must leave room for the bunkers and the player below them, even on the smallest playfield
+const LOWEST_WAVE_ROW: usize = Playfield::MIN.rows - 8;
+// Past the last defined level, each level is this much faster than the one before
+const REPEAT_SPEEDUP: f64 = 0.9;
+pub const MIN_MOVE_INTERVAL: Duration = Duration::from_millis(250);
+const MIN_FIRE_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl Display for LevelError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(err) => write!(f, "{}", err),
+            LevelError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+        }
+    }
+}
+
+impl Error for LevelError {}
+
+impl From<io::Error> for LevelError {
+    fn from(err: io::Error) -> Self {
+        LevelError::Io(err)
+    }
+}
+
+fn syntax_error(line: usize, column: usize, message: impl Into<String>) -> LevelError {
+    LevelError::Syntax {
+        line,
+        column,
+        message: message.into(),
+    }
+}
+
+// One invader of a wave, its top left corner relative to the top left corner of the wave
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placement {
+    pub x: usize,
+    pub y: usize,
+    pub kind: InvaderKind,
+    pub size: InvaderSize,
+}
+
+impl Placement {
+    fn bounds(&self) -> Rect {
+        self.kind.sprites(self.size)[0].bounds(self.x, self.y)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub move_interval: Duration,
+    pub fire_interval: Duration,
+    // Rows moved down each time the army reaches a side
+    pub drop: usize,
+    // Row of the first line of the wave
+    pub top: usize,
+    pub width: usize,
+    pub wave: Vec<Placement>,
+}
+
+impl Level {
+    // Column of the first line of the wave, so that it is centered
+    pub fn left(&self, playfield: Playfield) -> usize {
+        (playfield.cols - self.width) / 2
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Levels {
+    levels: Vec<Level>,
+}
+
+impl Levels {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN).expect("the built-in levels are valid")
+    }
+
+    pub fn parse(text: &str) -> Result<Self, LevelError> {
+        let mut levels = Vec::new();
+        let mut draft: Option<Draft> = None;
+        let mut in_wave = false;
+
+        for (idx, raw) in text.lines().enumerate() {
+            let line_no = idx + 1;
+            let line = raw.trim_end();
+            let trimmed = line.trim_start();
+            let indent = line.len() - trimmed.len();
+
+            if trimmed == "[level]" {
+                if let Some(draft) = draft.take() {
+                    levels.push(draft.finish()?);
+                }
+                draft = Some(Draft::new(line_no));
+                in_wave = false;
+                continue;
+            }
+
+            if in_wave {
+                if line.is_empty() {
+                    in_wave = false;
+                } else if let Some(draft) = draft.as_mut() {
+                    draft.add_row(line_no, line)?;
+                }
+                continue;
+            }
+
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let Some(draft) = draft.as_mut() else {
+                return Err(syntax_error(
+                    line_no,
+                    indent + 1,
+                    "expected [level] before any setting",
+                ));
+            };
+
+            if trimmed == "wave:" {
+                if draft.wave_line.is_some() {
+                    return Err(syntax_error(line_no, indent + 1, "level has a second wave"));
+                }
+                draft.wave_line = Some(line_no);
+                in_wave = true;
+                continue;
+            }
+
+            let Some((key, value)) = trimmed.split_once('=') else {
+                return Err(syntax_error(
+                    line_no,
+                    indent + 1,
+                    "expected `key = value`, `wave:` or `[level]`",
+                ));
+            };
+            let value_column =
+                indent + key.len() + 1 + (value.len() - value.trim_start().len()) + 1;
+            draft.set(line_no, indent + 1, key.trim(), value_column, value.trim())?;
+        }
+
+        if let Some(draft) = draft.take() {
+            levels.push(draft.finish()?);
+        }
+        if levels.is_empty() {
+            return Err(syntax_error(1, 1, "no [level] defined"));
+        }
+
+        Ok(Self { levels })
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    // Levels are numbered from 1, the ones past the end repeat the last level faster each time
+    pub fn get(&self, level: u32) -> Level {
+        let idx = (level.max(1) - 1) as usize;
+        if let Some(level) = self.levels.get(idx) {
+            return level.clone();
+        }
+
+        let mut repeated = self.levels[self.levels.len() - 1].clone();
+        let extra = (idx + 1 - self.levels.len()) as i32;
+        let speedup = REPEAT_SPEEDUP.powi(extra);
+        repeated.move_interval = repeated
+            .move_interval
+            .mul_f64(speedup)
+            .max(MIN_MOVE_INTERVAL);
+        repeated.fire_interval = repeated
+            .fire_interval
+            .mul_f64(speedup)
+            .max(MIN_FIRE_INTERVAL);
+        repeated
+    }
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+// A level being parsed, checked once all of its lines were read
+struct Draft {
+    line: usize,
+    move_interval: Option<u64>,
+    fire_interval: Option<u64>,
+    drop: Option<u64>,
+    top: Option<u64>,
+    wave_line: Option<usize>,
+    rows: usize,
+    // Rows down to the bottom of the lowest sprite
+    height: usize,
+    width: usize,
+    wave: Vec<Placement>,
+}
+
+impl Draft {
+    fn new(line: usize) -> Self {
+        Self {
+            line,
+            move_interval: None,
+            fire_interval: None,
+            drop: None,
+            top: None,
+            wave_line: None,
+            rows: 0,
+            height: 0,
+            width: 0,
+            wave: Vec::new(),
+        }
+    }
+
+    fn set(
+        &mut self,
+        line: usize,
+        key_column: usize,
+        key: &str,
+        value_column: usize,
+        value: &str,
+    ) -> Result<(), LevelError> {
+        let slot = match key {
+            "move" => &mut self.move_interval,
+            "fire" => &mut self.fire_interval,
+            "drop" => &mut self.drop,
+            "top" => &mut self.top,
+            _ => {
+                return Err(syntax_error(
+                    line,
+                    key_column,
+                    format!(
+                        "unknown setting `{}`, expected move, drop, fire or top",
+                        key
+                    ),
+                ))
+            }
+        };
+        if slot.is_some() {
+            return Err(syntax_error(
+                line,
+                key_column,
+                format!("`{}` is set twice", key),
+            ));
+        }
+
+        let number: u64 = value.parse().map_err(|_| {
+            syntax_error(
+                line,
+                value_column,
+                format!("`{}` is not a positive whole number", value),
+            )
+        })?;
+        let (minimum, maximum) = match key {
+            // Faster than this, the army's speed-up at each side would make it slower
+            "move" => (MIN_MOVE_INTERVAL.as_millis() as u64, u64::MAX),
+            // The rows above belong to the HUD and the saucer
+            "top" => (SAUCER_ROW as u64 + 1, LOWEST_WAVE_ROW as u64),
+            "drop" => (1, Playfield::MAX.rows as u64 - 1),
+            _ => (1, u64::MAX),
+        };
+        if number < minimum {
+            return Err(syntax_error(
+                line,
+                value_column,
+                format!("`{}` must be at least {}", key, minimum),
+            ));
+        }
+        if number > maximum {
+            return Err(syntax_error(
+                line,
+                value_column,
+                format!("`{}` must be at most {}", key, maximum),
+            ));
+        }
+
+        *slot = Some(number);
+        Ok(())
+    }
+
+    fn add_row(&mut self, line: usize, row: &str) -> Result<(), LevelError> {
+        let mut width = row.chars().count();
+        for (x, c) in row.chars().enumerate() {
+            if c == '.' {
+                continue;
+            }
+            let Some((kind, size)) = InvaderKind::from_char(c) else {
+                return Err(syntax_error(
+                    line,
+                    x + 1,
+                    format!(
+                        "unknown invader `{}`, expected A, B, C, D, their lower case or .",
+                        c
+                    ),
+                ));
+            };
+            let placement = Placement {
+                x,
+                y: self.rows,
+                kind,
+                size,
+            };
+            let bounds = placement.bounds();
+            if let Some(other) = self
+                .wave
+                .iter()
+                .find(|other| other.bounds().intersects(&bounds))
+            {
+                return Err(syntax_error(
+                    line,
+                    x + 1,
+                    format!(
+                        "invader overlaps the one on column {} of the wave's row {}",
+                        other.x + 1,
+                        other.y + 1
+                    ),
+                ));
+            }
+            width = width.max(bounds.x + bounds.width);
+            self.height = self.height.max(bounds.y + bounds.height);
+            self.wave.push(placement);
+        }
+        if width > Playfield::MIN.cols {
+            return Err(syntax_error(
+                line,
+                Playfield::MIN.cols + 1,
+                format!(
+                    "wave is wider than the {} columns of the smallest playfield",
+                    Playfield::MIN.cols
+                ),
+            ));
+        }
+
+        self.width = self.width.max(width);
+        self.rows += 1;
+
+        // Checked row by row, so the error points at the first row that is too low
+        match self.top {
+            Some(top) => check_bottom(line, top as usize + self.height - 1),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(self) -> Result<Level, LevelError> {
+        let missing = |key: &str| syntax_error(self.line, 1, format!("level has no `{}`", key));
+        let move_interval = self.move_interval.ok_or_else(|| missing("move"))?;
+        let fire_interval = self.fire_interval.ok_or_else(|| missing("fire"))?;
+        let drop = self.drop.ok_or_else(|| missing("drop"))?;
+        let top = self.top.ok_or_else(|| missing("top"))? as usize;
+
+        if self.wave.is_empty() {
+            let line = self.wave_line.unwrap_or(self.line);
+            return Err(syntax_error(line, 1, "level has no invaders"));
+        }
+        // Only fails here when `top` was set after the wave
+        check_bottom(self.line, top + self.height - 1)?;
+
+        Ok(Level {
+            move_interval: Duration::from_millis(move_interval),
+            fire_interval: Duration::from_millis(fire_interval),
+            drop: drop as usize,
+            top,
+            width: self.width,
+            wave: self.wave,
+        })
+    }
+}
+
+fn check_bottom(line: usize, bottom: usize) -> Result<(), LevelError> {
+    if bottom > LOWEST_WAVE_ROW {
+        Err(syntax_error(
+            line,
+            1,
+            format!(
+                "wave reaches row {}, below the lowest allowed row {}",
+                bottom, LOWEST_WAVE_ROW
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+// A single level with its wave at the top, in the format of the level files
+#[cfg(test)]
+impl Levels {
+    pub(crate) fn single_text(wave: &str, step: u64, drop: u64, fire: u64) -> String {
+        format!(
+            "[level]\nmove = {}\ndrop = {}\nfire = {}\ntop = 2\nwave:\n{}\n",
+            step, drop, fire, wave
+        )
+    }
+
+    pub(crate) fn single(wave: &str, step: u64, drop: u64, fire: u64) -> Self {
+        Self::parse(&Self::single_text(wave, step, drop, fire)).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave(rows: &str) -> Result<Levels, LevelError> {
+        Levels::parse(&Levels::single_text(rows, 1000, 1, 1000))
+    }
+
+    fn syntax(err: LevelError) -> (usize, usize, String) {
+        match err {
+            LevelError::Syntax {
+                line,
+                column,
+                message,
+            } => (line, column, message),
+            LevelError::Io(err) => panic!("unexpected {}", err),
+        }
+    }
+
+    // A level with the given settings, one per line, and a single invader
+    fn settings(lines: &str) -> Result<Levels, LevelError> {
+        Levels::parse(&format!("[level]\n{}\nwave:\nA\n", lines))
+    }
+
+    const SETTINGS: &str = "move = 1000\ndrop = 1\nfire = 1000\ntop = 2";
+
+    #[test]
+    fn settings_are_checked() {
+        let cases = [
+            (
+                "move = 1000\nspeed = 3",
+                (
+                    3,
+                    1,
+                    "unknown setting `speed`, expected move, drop, fire or top",
+                ),
+            ),
+            ("move = 1000\nmove = 900", (3, 1, "`move` is set twice")),
+            (
+                "move = fast",
+                (2, 8, "`fast` is not a positive whole number"),
+            ),
+            ("move = -5", (2, 8, "`-5` is not a positive whole number")),
+            ("move = 100", (2, 8, "`move` must be at least 250")),
+            ("top = 1", (2, 7, "`top` must be at least 2")),
+            (
+                "top = 18446744073709551615",
+                (2, 7, "`top` must be at most 12"),
+            ),
+            ("drop = 30", (2, 8, "`drop` must be at most 29")),
+            ("drop = 0", (2, 8, "`drop` must be at least 1")),
+        ];
+        for (lines, (line, column, message)) in cases {
+            let err = syntax(settings(lines).unwrap_err());
+            assert_eq!(err, (line, column, message.to_string()), "{}", lines);
+        }
+    }
+
+    #[test]
+    fn every_setting_is_required() {
+        for key in ["move", "drop", "fire", "top"] {
+            let lines: Vec<&str> = SETTINGS
+                .lines()
+                .filter(|line| !line.starts_with(key))
+                .collect();
+            let err = syntax(settings(&lines.join("\n")).unwrap_err());
+            assert_eq!(err, (1, 1, format!("level has no `{}`", key)));
+        }
+        assert!(settings(SETTINGS).is_ok());
+    }
+
+    #[test]
+    fn unknown_invaders_are_reported_where_they_are() {
+        let (line, column, message) = syntax(wave("A.B\n.X.").unwrap_err());
+        assert_eq!((line, column), (8, 2));
+        assert_eq!(
+            message,
+            "unknown invader `X`, expected A, B, C, D, their lower case or ."
+        );
+    }
+
+    #[test]
+    fn large_invaders_take_three_columns_and_two_rows() {
+        let levels = wave("a...B\n....C").unwrap();
+        let level = levels.get(1);
+        assert_eq!(level.width, 5);
+        let large = &level.wave[0];
+        assert_eq!((large.x, large.y, large.size), (0, 0, InvaderSize::Large));
+        assert_eq!(large.bounds().width, 3);
+        assert_eq!(large.bounds().height, 2);
+    }
+
+    #[test]
+    fn invaders_must_not_overlap() {
+        let (line, column, message) = syntax(wave("a.A").unwrap_err());
+        assert_eq!((line, column), (7, 3));
+        assert_eq!(
+            message,
+            "invader overlaps the one on column 1 of the wave's row 1"
+        );
+
+        // Under a large invader
+        let (line, column, message) = syntax(wave("..a\n...B").unwrap_err());
+        assert_eq!((line, column), (8, 4));
+        assert_eq!(
+            message,
+            "invader overlaps the one on column 3 of the wave's row 1"
+        );
+    }
+
+    #[test]
+    fn a_large_invader_counts_in_the_width() {
+        let rows = format!("{}a", ".".repeat(Playfield::MIN.cols - 2));
+        let (line, column, _) = syntax(wave(&rows).unwrap_err());
+        assert_eq!((line, column), (7, Playfield::MIN.cols + 1));
+    }
+
+    #[test]
+    fn the_builtin_levels_have_large_invaders() {
+        let levels = Levels::builtin();
+        assert!((1..=levels.len() as u32).any(|number| {
+            levels
+                .get(number)
+                .wave
+                .iter()
+                .any(|placement| placement.size == InvaderSize::Large)
+        }));
+    }
+}
@@ -1,8 +1,32 @@
+pub mod animation;
+pub mod audio;
+pub mod bindings;
+pub mod bunkers;
+pub mod compositor;
+pub mod controller;
 pub mod frame;
+pub mod game;
+pub mod highscores;
+pub mod hud;
 pub mod invaders;
+pub mod levels;
+pub mod net;
+pub mod particles;
+pub mod paths;
 pub mod player;
+pub mod playfield;
+pub mod powerups;
 pub mod render;
+pub mod replay;
+pub mod rng;
+pub mod saucer;
+pub mod screens;
 pub mod shot;
+pub mod simulation;
+pub mod sprite;
 
-pub const NUM_ROWS: usize = 20;
-pub const NUM_COLS: usize = 40;
+// Top row of the frame, kept free of gameplay for the score and lives
+pub const HUD_ROW: usize = 0;
+// Just under the HUD, only crossed by the mystery saucer
+pub const SAUCER_ROW: usize = HUD_ROW + 1;
+pub const DEFAULT_LIVES: u32 = 3;
@@ -1,7 +1,13 @@
 // standard library imports
-use std::sync::mpsc;
+use std::fs::{self, File};
+use std::io::Write;
+use std::mem;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
 use std::time::Instant;
-use std::{error::Error, io, thread, time::Duration};
+use std::{error::Error, io, path::PathBuf, process, thread, time::Duration};
 
 // crossterm library imports
 use crossterm::{
@@ -13,115 +19,834 @@ use crossterm::{
 
 // other imports
 use crate::event::Event;
-use invaders::frame::Drawable;
-use invaders::invaders::Invaders;
-use invaders::{frame, player::Player, render};
-use rusty_audio::Audio;
+use clap::Parser;
+use invaders::audio::{Audio, AudioSink, Sound};
+use invaders::bindings::{Action, Bindings, BindingsError};
+use invaders::compositor::Compositor;
+use invaders::controller::{Bot, Controller};
+use invaders::frame::{Drawable, Frame, Style};
+use invaders::game::{Game, GameEvent, Settings, MAX_PLAYERS, TICK};
+use invaders::highscores::{HighScores, NameEntry};
+use invaders::hud::{EffectTimers, Hud};
+use invaders::levels::{LevelError, Levels};
+use invaders::net::{Arrival, Broadcast, HostConnection, RemotePlayer};
+use invaders::playfield::Playfield;
+use invaders::render::{ColorMode, RenderStats, Renderer, TerminalRenderer};
+use invaders::replay::Replay;
+use invaders::rng::Rng;
+use invaders::screens::{Choice, Menu, Screen};
+use invaders::simulation::{self, Report};
+use invaders::{frame, rng, DEFAULT_LIVES};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    let mut audio = Audio::new();
-    audio.add("explode", "explode.wav");
-    audio.add("lose", "lose.wav");
-    audio.add("move", "move.wav");
-    audio.add("pew", "pew.wav");
-    audio.add("startup", "startup.wav");
-    audio.add("win", "win.wav");
-    audio.play("startup");
-
-    // Terminal
-    let mut stdout = io::stdout();
-    terminal::enable_raw_mode()?;
-    stdout.execute(EnterAlternateScreen)?;
-    stdout.execute(Hide)?;
+// Most ticks simulated at once when the game loop falls behind
+const MAX_CATCH_UP_TICKS: u32 = 5;
 
-    // Render loop in a separate thread
-    let (render_tx, render_rx) = mpsc::channel();
-    let render_handle = thread::spawn(move || {
-        let mut last_frame = frame::new_frame();
-        let mut stdout = io::stdout();
-        render::render(&mut stdout, &last_frame, &last_frame, true);
-        loop {
-            let cur_frame = match render_rx.recv() {
-                Ok(x) => x,
-                Err(_) => break,
-            };
-            render::render(&mut stdout, &last_frame, &cur_frame, false);
-            last_frame = cur_frame;
+// What the game loop sends to the render thread
+enum RenderMessage {
+    Frame(Frame),
+    // New terminal size, forcing a full redraw
+    Resize(u16, u16),
+}
+
+/// Terminal Space Invaders
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// 2 for co-op on the same keyboard, see --print-default-config for the second player's keys
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=MAX_PLAYERS as i64))]
+    players: u8,
+
+    /// Number of lives each player starts with
+    #[arg(long, default_value_t = DEFAULT_LIVES, value_parser = clap::value_parser!(u32).range(1..))]
+    lives: u32,
+
+    /// Level definitions file, replacing the built-in levels
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    levels: Option<PathBuf>,
+
+    /// Draw without colors, for terminals that do not support them
+    #[arg(long)]
+    mono: bool,
+
+    /// Draw shots, bombs and power-ups with Unicode half blocks, moving half a row at a time
+    #[arg(long)]
+    half_blocks: bool,
+
+    /// Log what each rendered frame cost to this file, one line of
+    /// "cells_changed cells_written runs bytes" per frame, and print a summary on exit
+    #[arg(long, value_name = "FILE")]
+    render_stats: Option<PathBuf>,
+
+    /// Record the game to this file, to watch it again with --replay
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+
+    /// Play back a recorded game, with its seed, players, lives and levels
+    #[arg(long, value_name = "FILE")]
+    replay: Option<PathBuf>,
+
+    /// Start with the sound off, it can be switched back on in game
+    #[arg(long)]
+    mute: bool,
+
+    /// Host a game for two over the network, waiting for the second player on this address,
+    /// e.g. 0.0.0.0:7878
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["join", "replay", "players"])]
+    host: Option<String>,
+
+    /// Join a game hosted with --host at this address, as the second player
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["replay", "record", "players", "lives", "levels"])]
+    join: Option<String>,
+
+    /// Let spectators watch the game with --watch on this address, e.g. 0.0.0.0:7880
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["join", "watch"])]
+    broadcast: Option<String>,
+
+    /// Watch a game started with --broadcast at this address
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["join", "host", "replay", "record"])]
+    watch: Option<String>,
+
+    /// Let a bot play the local players, on the title screen this is the DEMO choice
+    #[arg(long)]
+    autoplay: bool,
+
+    /// Let a bot play this many games without a terminal, then print how it went
+    #[arg(long, value_name = "N", conflicts_with_all = ["join", "watch", "host", "replay", "record", "broadcast"])]
+    simulate: Option<u32>,
+
+    /// Print the default key bindings in the format of the config file, then exit
+    #[arg(long)]
+    print_default_config: bool,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    if cli.print_default_config {
+        print!("{}", Bindings::default());
+        return Ok(());
+    }
+    if let Some(games) = cli.simulate {
+        simulate(&cli, games);
+        return Ok(());
+    }
+    // Checked before touching the terminal, so that errors are readable
+    let bindings = match Bindings::default_path().map(|path| (Bindings::load(&path), path)) {
+        Some((Ok(bindings), _)) => bindings,
+        // No config file is the usual case, the defaults are what it asks for
+        Some((Err(BindingsError::Io(err)), _)) if err.kind() == io::ErrorKind::NotFound => {
+            Bindings::default()
+        }
+        Some((Err(err), path)) => {
+            eprintln!(
+                "Using the default keys, could not read {}: {}",
+                path.display(),
+                err
+            );
+            Bindings::default()
         }
+        None => Bindings::default(),
+    };
+    let stats_log = cli.render_stats.as_ref().map(|path| {
+        File::create(path).unwrap_or_else(|err| {
+            eprintln!("Could not create {}: {}", path.display(), err);
+            process::exit(1);
+        })
     });
+    if let Some(addr) = &cli.join {
+        return play_remote(&cli, addr, &bindings, stats_log, false);
+    }
+    if let Some(addr) = &cli.watch {
+        return play_remote(&cli, addr, &bindings, stats_log, true);
+    }
+    let replay = match &cli.replay {
+        Some(path) => match Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                eprintln!("Invalid replay file {}: {}", path.display(), err);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    // A replay brings the levels it was recorded with
+    let (levels, levels_text) = match &replay {
+        Some(replay) => match replay.levels.as_deref().map(Levels::parse) {
+            None => (Levels::builtin(), None),
+            Some(Ok(levels)) => (levels, replay.levels.clone()),
+            Some(Err(err)) => {
+                eprintln!("Invalid levels in the replay file: {}", err);
+                process::exit(1);
+            }
+        },
+        None => load_levels(cli.levels.as_deref()),
+    };
+    let (term_cols, term_rows) = terminal::size()?;
+    let playfield = match &replay {
+        Some(replay) => {
+            Some(replay.playfield).filter(|playfield| playfield.fits_in(term_cols, term_rows))
+        }
+        None => Playfield::fit(term_cols, term_rows),
+    };
+    let Some(mut playfield) = playfield else {
+        let needed = replay
+            .as_ref()
+            .map_or(Playfield::MIN, |replay| replay.playfield);
+        eprintln!(
+            "Terminal too small: needs at least {}x{}, got {}x{}",
+            needed.cols, needed.rows, term_cols, term_rows
+        );
+        process::exit(1);
+    };
+    let seed = replay
+        .as_ref()
+        .map_or_else(rng::random_seed, |replay| replay.seed);
+    let players = match &replay {
+        Some(replay) => replay.players,
+        None if cli.host.is_some() => 2,
+        None => cli.players as usize,
+    };
+    // When hosting, the second player's keys are on the other side
+    let local_players = if cli.host.is_some() { 1 } else { players };
+    let lives = replay.as_ref().map_or(cli.lives, |replay| replay.lives);
+
+    // The second player joins before the terminal is taken over, the playfield must fit both
+    let remote = cli
+        .host
+        .as_ref()
+        .map(|addr| wait_for_player(addr, &mut playfield));
+    let mut disconnected = false;
+    let broadcast = cli
+        .broadcast
+        .as_ref()
+        .map(|addr| match TcpListener::bind(addr) {
+            Ok(listener) => Broadcast::start(listener),
+            Err(err) => {
+                eprintln!("Could not broadcast on {}: {}", addr, err);
+                process::exit(1);
+            }
+        });
 
-    let mut player = Player::new();
+    let mut audio = Audio::open(cli.mute);
+    audio.play(Sound::Startup);
+
+    // Terminal, and the render loop in a separate thread
+    let (render_tx, render_handle) = spawn_render_thread(&cli, stats_log, term_cols, term_rows)?;
+
+    let high_scores_path = HighScores::default_path();
+    let mut high_scores = high_scores_path
+        .as_deref()
+        .map(HighScores::load)
+        .unwrap_or_default();
+
+    let new_game = |seed: u64, playfield: Playfield| {
+        Game::new(Settings {
+            players,
+            lives,
+            seed,
+            levels: levels.clone(),
+            playfield,
+        })
+    };
+    let new_recording = |seed: u64, playfield: Playfield| {
+        cli.record
+            .as_ref()
+            .map(|_| Replay::new(seed, players, lives, playfield, levels_text.clone()))
+    };
+    let mut game = new_game(seed, playfield);
+    // Only the last game played is recorded
+    let mut recording = new_recording(seed, playfield);
+    let replaying = replay.is_some();
+    let mut steps = replay.map(|replay| replay.steps.into_iter());
+    // Replays go straight to the game and end with it
+    let mut screen = if replaying {
+        Screen::Playing
+    } else {
+        Screen::Title(Menu::title(high_scores.top()))
+    };
+    // Bots play the local players in demos, their games do not make the high scores
+    let mut autoplay = cli.autoplay;
+    let mut bots: Vec<Bot> = (0..local_players).map(|_| Bot::new()).collect();
+    // Game time not simulated yet, consumed one tick at a time
+    let mut accumulator = Duration::ZERO;
     let mut instant = Instant::now();
-    let mut invaders = Invaders::new();
+    // Kept up to date by every resize. The game is paused while it does not fit, the next one is
+    // sized to the terminal again.
+    let mut term_size = (term_cols, term_rows);
+    // The remote player's terminal has to fit the playfield too
+    let refit = |(cols, rows): (u16, u16)| {
+        remote
+            .is_none()
+            .then(|| Playfield::fit(cols, rows))
+            .flatten()
+    };
+    // Given since the last tick, which gets them all at once so that replays see them the same way
+    let mut commands = Vec::new();
+    let mut save_result = Ok(());
 
     'gameloop: loop {
-        // Pre-frame init
-        let delta = instant.elapsed();
-        instant = Instant::now();
-        let mut curr_frame = frame::new_frame();
-
-        // Input
-        while event::poll(Duration::default())? {
-            if let Event::Key(key_event) = event::read()? {
-                match key_event.code {
-                    KeyCode::Char(' ') | KeyCode::Enter => {
-                        if player.shoot() {
-                            audio.play("pew");
-                        }
+        // Input, sleeping until it comes or the next tick is due
+        let mut timeout = TICK.saturating_sub(accumulator);
+        while event::poll(timeout)? {
+            timeout = Duration::ZERO;
+            let key_event = match event::read()? {
+                Event::Resize(cols, rows) => {
+                    resized(&render_tx, &mut term_size, cols, rows);
+                    // Nothing happened yet in a game still behind the title screen
+                    if let (Screen::Title(_), Some(refitted)) = (&screen, refit(term_size)) {
+                        playfield = refitted;
+                        game = new_game(seed, playfield);
+                        recording = new_recording(seed, playfield);
                     }
-                    KeyCode::Esc | KeyCode::Char('q') => {
-                        audio.play("lose");
+                    continue;
+                }
+                Event::Key(key_event) => key_event,
+                _ => continue,
+            };
+            let fits = playfield.fits_in(term_size.0, term_size.1);
+
+            let action = bindings.action(key_event.code);
+            let paused = matches!(screen, Screen::Paused(_));
+            if action == Some(Action::Mute) {
+                audio.toggle_mute();
+                continue;
+            }
+            // Too small to show the menus, quitting does not ask first
+            if !fits {
+                match action {
+                    Some(Action::Quit) => {
+                        audio.play(Sound::Lose);
                         break 'gameloop;
                     }
-                    KeyCode::Left => player.move_left(),
-                    KeyCode::Right => player.move_right(),
-                    _ => {}
+                    Some(Action::Pause) => {}
+                    _ => continue,
+                }
+            }
+            let menu = match &mut screen {
+                Screen::Playing => {
+                    match action {
+                        Some(Action::Pause) => screen = Screen::Paused(Menu::pause()),
+                        Some(Action::Quit) => {
+                            // Asks first, with quitting preselected
+                            let mut menu = Menu::pause();
+                            menu.down();
+                            screen = Screen::Paused(menu);
+                        }
+                        // Replays and bots only take their own commands
+                        _ if replaying || autoplay => {}
+                        Some(action) => {
+                            if let Some((player, command)) = action.command() {
+                                if player < local_players {
+                                    commands.push((player, command));
+                                }
+                            }
+                        }
+                        None => {}
+                    }
+                    continue;
+                }
+                Screen::Title(menu) | Screen::Paused(menu) | Screen::GameOver(menu) => menu,
+            };
+            // Arrows and Enter always work in menus, unless bound to something else
+            let choice = match (action, key_event.code) {
+                (Some(Action::Fire), _) | (None, KeyCode::Enter) => menu.selected(),
+                (Some(Action::Pause), _) if paused => Choice::Resume,
+                (Some(Action::Pause | Action::Quit), _) => Choice::Quit,
+                (Some(Action::MoveLeft), _) | (None, KeyCode::Up) => {
+                    menu.up();
+                    continue;
+                }
+                (Some(Action::MoveRight), _) | (None, KeyCode::Down) => {
+                    menu.down();
+                    continue;
+                }
+                _ => continue,
+            };
+            match choice {
+                Choice::Start => {
+                    autoplay = cli.autoplay;
+                    screen = Screen::Playing;
+                }
+                Choice::Demo => {
+                    autoplay = true;
+                    screen = Screen::Playing;
+                }
+                Choice::Resume => screen = Screen::Playing,
+                Choice::Restart => {
+                    // After a demo, playing again is up to the players
+                    autoplay = cli.autoplay;
+                    if let Some(refitted) = refit(term_size) {
+                        playfield = refitted;
+                    }
+                    let seed = rng::random_seed();
+                    game = new_game(seed, playfield);
+                    recording = new_recording(seed, playfield);
+                    bots = (0..local_players).map(|_| Bot::new()).collect();
+                    commands.clear();
+                    audio.play(Sound::Startup);
+                    screen = Screen::Playing;
+                }
+                Choice::Quit => {
+                    audio.play(Sound::Lose);
+                    break 'gameloop;
+                }
+            }
+        }
+
+        if let Some(remote) = &remote {
+            match remote.commands() {
+                Ok(remote_commands) => {
+                    if matches!(screen, Screen::Playing) {
+                        commands.extend(remote_commands.into_iter().map(|command| (1, command)));
+                    }
+                }
+                Err(_) => {
+                    disconnected = true;
+                    break 'gameloop;
                 }
             }
         }
 
-        // Updates
-        player.update(delta);
-        if invaders.update(delta) {
-            audio.play("move");
+        // Updates, in fixed ticks so that the game runs the same however fast the machine is
+        accumulator += instant.elapsed();
+        instant = Instant::now();
+        // Nothing moves while paused, in menus or when the game does not fit
+        let fits = playfield.fits_in(term_size.0, term_size.1);
+        if !fits || !matches!(screen, Screen::Playing) {
+            accumulator = Duration::ZERO;
+        }
+        // After a stall the game slows down rather than jumping ahead
+        accumulator = accumulator.min(TICK * MAX_CATCH_UP_TICKS);
+        while accumulator >= TICK {
+            accumulator -= TICK;
+            let mut delta = TICK;
+            if let Some(steps) = steps.as_mut() {
+                let Some(step) = steps.next() else {
+                    break 'gameloop;
+                };
+                delta = step.delta;
+                commands = step.commands;
+            } else if autoplay {
+                for (player, bot) in bots.iter_mut().enumerate() {
+                    let bot_commands = bot.commands(&game, player);
+                    commands.extend(bot_commands.into_iter().map(|command| (player, command)));
+                }
+            }
+            for (player, command) in commands.iter() {
+                game.command(*player, *command);
+            }
+            game.update(delta);
+            match recording.as_mut() {
+                Some(recording) => recording.push(delta, mem::take(&mut commands)),
+                None => commands.clear(),
+            }
+            if game.is_over() {
+                break;
+            }
+        }
+        for game_event in game.drain_events() {
+            audio.play(sound_for(game_event));
         }
 
-        if player.detect_hits(&mut invaders) {
-            audio.play("explode");
+        // Lost?
+        if game.is_over() && matches!(screen, Screen::Playing) {
+            if replaying {
+                break 'gameloop;
+            }
+            if !autoplay && high_scores.qualifies(game.score()) {
+                if let Some(name) = enter_name(&render_tx, &mut term_size, playfield, game.score())?
+                {
+                    high_scores.insert(&name, game.score());
+                    if let Some(path) = &high_scores_path {
+                        save_result = high_scores.save(path);
+                    }
+                    show_high_scores(&render_tx, &mut term_size, playfield, &high_scores)?;
+                }
+            }
+            screen = Screen::GameOver(Menu::game_over(game.score(), game.level()));
         }
 
         // Draw and render
-        let drawables: Vec<&dyn Drawable> = vec![&player, &invaders];
-        for drawable in drawables {
-            drawable.draw(&mut curr_frame);
+        let mut curr_frame = frame::new_frame(playfield);
+        let hud = Hud::new(&game, high_scores.top());
+        let timers = EffectTimers::new(&game);
+        let mut compositor = Compositor::new();
+        match &screen {
+            Screen::Title(menu) => compositor.add(menu),
+            Screen::Playing => {
+                game.add_layers(&mut compositor);
+                compositor.add(&timers);
+                compositor.add(&hud);
+            }
+            Screen::Paused(menu) | Screen::GameOver(menu) => {
+                game.add_layers(&mut compositor);
+                compositor.add(&timers);
+                compositor.add(&hud);
+                compositor.add(menu);
+            }
         }
-
-        // Ignore result as the first frames send will fail until the render thread will be started
-        let _ = render_tx.send(curr_frame).unwrap();
-
-        // Wait for the slower render thread
-        thread::sleep(Duration::from_millis(1));
-
-        // Win or lose?
-        if invaders.all_killed() {
-            audio.play("win");
-            break 'gameloop;
+        if cli.half_blocks {
+            compositor.draw_half_blocks(&mut curr_frame);
+        } else {
+            compositor.draw(&mut curr_frame);
         }
 
-        if invaders.reach_bottom() {
-            audio.play("lose");
+        if let Some(broadcast) = &broadcast {
+            broadcast.send_frame(curr_frame.clone());
+        }
+        if let Some(remote) = &remote {
+            if remote.send_frame(curr_frame.clone()).is_err() {
+                disconnected = true;
+                break 'gameloop;
+            }
+        }
+        // Only fails once the render thread stopped on an error, reported after cleanup
+        if render_tx.send(RenderMessage::Frame(curr_frame)).is_err() {
             break 'gameloop;
         }
     }
+    // Hangs up on the other player and the spectators
+    drop(remote);
+    drop(broadcast);
+
+    let record_result = match (&recording, &cli.record) {
+        (Some(recording), Some(path)) => recording.save(path),
+        _ => Ok(()),
+    };
 
     // Cleanup
     drop(render_tx);
     // The render_rx will fail as the render_tx was dropped, so the render_handel will join
-    render_handle.join().unwrap();
+    let render_result = render_handle
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("render thread panicked")));
     audio.wait();
+    restore_terminal()?;
+
+    // Reported once the terminal is back to normal
+    if disconnected {
+        eprintln!("The other player disconnected");
+    }
+    if let Err(err) = save_result {
+        eprintln!("Could not save the high scores: {}", err);
+    }
+    if let Err(err) = record_result {
+        eprintln!("Could not save the recording: {}", err);
+    }
+    report_render_result(&cli, render_result);
+
+    Ok(())
+}
+
+// The levels and the text they were read from, which replays keep. Exits when the file is invalid.
+fn load_levels(path: Option<&Path>) -> (Levels, Option<String>) {
+    let Some(path) = path else {
+        return (Levels::builtin(), None);
+    };
+    let loaded = fs::read_to_string(path)
+        .map_err(LevelError::from)
+        .and_then(|text| Ok((Levels::parse(&text)?, Some(text))));
+    match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("Invalid levels file {}: {}", path.display(), err);
+            process::exit(1);
+        }
+    }
+}
+
+// Headless games played by bots, to tune the difficulty
+fn simulate(cli: &Cli, games: u32) {
+    let (levels, _) = load_levels(cli.levels.as_deref());
+
+    let mut seeds = Rng::new(rng::random_seed());
+    let mut report = Report::default();
+    for _ in 0..games {
+        report.add(simulation::play(Settings {
+            players: cli.players as usize,
+            lives: cli.lives,
+            seed: seeds.next_u64(),
+            levels: levels.clone(),
+            playfield: Playfield::default(),
+        }));
+    }
+    println!("{}", report);
+}
+
+// Takes over the terminal and starts drawing whatever is sent to the returned channel, logging the
+// stats of each frame to the given file
+fn spawn_render_thread(
+    cli: &Cli,
+    mut stats_log: Option<File>,
+    term_cols: u16,
+    term_rows: u16,
+) -> io::Result<(Sender<RenderMessage>, JoinHandle<io::Result<RenderStats>>)> {
+    let color_mode = if cli.mono {
+        ColorMode::Monochrome
+    } else {
+        ColorMode::Color
+    };
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    stdout.execute(EnterAlternateScreen)?;
+    stdout.execute(Hide)?;
+
+    let (render_tx, render_rx) = mpsc::channel();
+    let render_handle = thread::spawn(move || -> io::Result<RenderStats> {
+        let mut renderer = TerminalRenderer::new(io::stdout(), term_cols, term_rows, color_mode);
+        let mut totals = RenderStats::default();
+        while let Ok(message) = render_rx.recv() {
+            match message {
+                RenderMessage::Frame(curr_frame) => {
+                    let stats = renderer.render(&curr_frame)?;
+                    if let Some(log) = stats_log.as_mut() {
+                        writeln!(
+                            log,
+                            "{} {} {} {}",
+                            stats.cells_changed,
+                            stats.cells_written,
+                            stats.runs,
+                            stats.bytes_written
+                        )?;
+                    }
+                    totals += stats;
+                }
+                RenderMessage::Resize(cols, rows) => renderer.resize(cols, rows),
+            }
+        }
+        Ok(totals)
+    });
+    Ok((render_tx, render_handle))
+}
+
+fn restore_terminal() -> io::Result<()> {
+    let mut stdout = io::stdout();
     stdout.execute(Show)?;
     stdout.execute(LeaveAlternateScreen)?;
-    terminal::disable_raw_mode()?;
+    terminal::disable_raw_mode()
+}
+
+// Exits on rendering errors, prints the summary asked for with --render-stats
+fn report_render_result(cli: &Cli, render_result: io::Result<RenderStats>) {
+    let render_totals = match render_result {
+        Ok(totals) => totals,
+        Err(err) => {
+            eprintln!("Rendering failed: {}", err);
+            process::exit(1);
+        }
+    };
+    if cli.render_stats.is_some() && render_totals.frames > 0 {
+        let frames = render_totals.frames;
+        println!(
+            "Rendered {} frames, per frame: {:.1} cells changed, {:.1} cells written, {:.1} runs, {:.1} bytes",
+            frames,
+            render_totals.cells_changed as f64 / frames as f64,
+            render_totals.cells_written as f64 / frames as f64,
+            render_totals.runs as f64 / frames as f64,
+            render_totals.bytes_written as f64 / frames as f64,
+        );
+    }
+}
+
+// Blocks until the second player joins, shrinking the playfield to fit their terminal too
+fn wait_for_player(addr: &str, playfield: &mut Playfield) -> RemotePlayer {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("Could not host on {}: {}", addr, err);
+            process::exit(1);
+        }
+    };
+    eprintln!("Waiting for the second player to join on {}", addr);
+
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                eprintln!("Could not accept players on {}: {}", addr, err);
+                process::exit(1);
+            }
+        };
+        let arrival = match Arrival::greet(stream) {
+            Ok(arrival) => arrival,
+            // Someone else knocking, or too slow to say hello, keep waiting
+            Err(_) => continue,
+        };
+        let term_cols = (playfield.cols as u16).min(arrival.term_cols);
+        let term_rows = (playfield.rows as u16).min(arrival.term_rows);
+        let Some(shared) = Playfield::fit(term_cols, term_rows) else {
+            let reason = format!(
+                "terminal too small, needs at least {}x{}",
+                Playfield::MIN.cols,
+                Playfield::MIN.rows
+            );
+            let _ = arrival.refuse(&reason);
+            continue;
+        };
+        match arrival.welcome() {
+            Ok(remote) => {
+                *playfield = shared;
+                return remote;
+            }
+            Err(_) => continue,
+        }
+    }
+}
+
+// Joins a game hosted elsewhere, or only watches it: keys go to the host, frames come back
+fn play_remote(
+    cli: &Cli,
+    addr: &str,
+    bindings: &Bindings,
+    stats_log: Option<File>,
+    watching: bool,
+) -> Result<(), Box<dyn Error>> {
+    let (term_cols, term_rows) = terminal::size()?;
+    let connection = if watching {
+        HostConnection::watch(addr)
+    } else {
+        HostConnection::connect(addr, term_cols, term_rows)
+    };
+    let (mut connection, mut frames) = match connection {
+        Ok(connection) => connection,
+        Err(err) => {
+            eprintln!("Could not connect to {}: {}", addr, err);
+            process::exit(1);
+        }
+    };
+
+    let (render_tx, render_handle) = spawn_render_thread(cli, stats_log, term_cols, term_rows)?;
+    let frame_tx = render_tx.clone();
+    let receiving = thread::spawn(move || -> io::Result<()> {
+        loop {
+            let curr_frame = frames.next_frame()?.clone();
+            if frame_tx.send(RenderMessage::Frame(curr_frame)).is_err() {
+                return Ok(());
+            }
+        }
+    });
+
+    let mut quit = false;
+    while !receiving.is_finished() {
+        if !event::poll(Duration::from_millis(50))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Resize(cols, rows) => {
+                let _ = render_tx.send(RenderMessage::Resize(cols, rows));
+            }
+            Event::Key(key_event) => match bindings.action(key_event.code) {
+                Some(Action::Quit) => {
+                    quit = true;
+                    break;
+                }
+                _ if watching => {}
+                // The guest plays with the first player's keys
+                Some(action) => {
+                    if let Some((0, command)) = action.command() {
+                        if connection.send(command).is_err() {
+                            break;
+                        }
+                    }
+                }
+                None => {}
+            },
+            _ => {}
+        }
+    }
 
+    // Hanging up also ends the receiving thread
+    drop(connection);
+    let receive_result = receiving
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("receiving thread panicked")));
+    drop(render_tx);
+    let render_result = render_handle
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("render thread panicked")));
+    restore_terminal()?;
+
+    if !quit {
+        match receive_result {
+            Err(err) if err.kind() != io::ErrorKind::UnexpectedEof => {
+                eprintln!("Lost the connection to the host: {}", err)
+            }
+            _ => eprintln!("The game ended"),
+        }
+    }
+    report_render_result(cli, render_result);
     Ok(())
 }
+
+// Every resize of the local game goes through here, so that the renderer and the game loop agree
+// on the size of the terminal
+fn resized(render_tx: &Sender<RenderMessage>, term_size: &mut (u16, u16), cols: u16, rows: u16) {
+    *term_size = (cols, rows);
+    let _ = render_tx.send(RenderMessage::Resize(cols, rows));
+}
+
+fn enter_name(
+    render_tx: &Sender<RenderMessage>,
+    term_size: &mut (u16, u16),
+    playfield: Playfield,
+    score: u32,
+) -> io::Result<Option<String>> {
+    let mut entry = NameEntry::new(score);
+    loop {
+        let mut curr_frame = frame::new_frame(playfield);
+        entry.draw(&mut curr_frame);
+        let _ = render_tx.send(RenderMessage::Frame(curr_frame));
+
+        match event::read()? {
+            Event::Resize(cols, rows) => resized(render_tx, term_size, cols, rows),
+            Event::Key(key_event) => match key_event.code {
+                KeyCode::Enter if entry.done() => return Ok(Some(entry.name)),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Backspace => entry.pop(),
+                KeyCode::Char(c) => entry.push(c),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+}
+
+fn show_high_scores(
+    render_tx: &Sender<RenderMessage>,
+    term_size: &mut (u16, u16),
+    playfield: Playfield,
+    high_scores: &HighScores,
+) -> io::Result<()> {
+    let mut curr_frame = frame::new_frame(playfield);
+    high_scores.draw(&mut curr_frame);
+    frame::draw_text_centered(
+        &mut curr_frame,
+        playfield.rows - 3,
+        "PRESS ANY KEY",
+        Style::DEFAULT.dim(),
+    );
+    let _ = render_tx.send(RenderMessage::Frame(curr_frame.clone()));
+
+    // Wait for a key press, drawing the table again after a resize cleared the screen
+    loop {
+        match event::read()? {
+            Event::Key(_) => return Ok(()),
+            Event::Resize(cols, rows) => {
+                resized(render_tx, term_size, cols, rows);
+                let _ = render_tx.send(RenderMessage::Frame(curr_frame.clone()));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn sound_for(game_event: GameEvent) -> Sound {
+    match game_event {
+        GameEvent::ShotFired { .. } => Sound::Pew,
+        GameEvent::InvadersMoved => Sound::Move,
+        GameEvent::InvaderDamaged { .. } => Sound::Move,
+        GameEvent::InvaderKilled { .. } | GameEvent::PlayerHit { .. } => Sound::Explode,
+        GameEvent::LevelCleared { .. } | GameEvent::PowerUpCollected { .. } => Sound::Win,
+        GameEvent::NextLevel { .. } => Sound::Startup,
+        GameEvent::Lose => Sound::Lose,
+    }
+}
@@ -0,0 +1,636 @@
+use crate::frame::{self, Attributes, Cell, Color, Frame, Style};
+use crate::game::Command;
+use crate::playfield::Playfield;
+use crate::render::{RenderStats, Renderer};
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError};
+use std::thread;
+use std::time::Duration;
+
+// First word of the hello line, bumped whenever the protocol changes
+const PROTOCOL: &str = "invaders-net v1";
+const MAX_LINE_LEN: usize = 256;
+// Spectators who take longer than this to say hello or to take a frame are dropped
+const SPECTATOR_TIMEOUT: Duration = Duration::from_secs(2);
+// Players who take longer than this to say hello are turned away
+const HELLO_TIMEOUT: Duration = Duration::from_secs(2);
+// Players who take longer than this to take a frame are taken for gone
+const PLAYER_TIMEOUT: Duration = Duration::from_secs(2);
+// Message tag of a frame, the only message a host sends once the game started
+const FRAME: u8 = b'F';
+// x and y as u16, glyph as u32, fg, bg and attributes as u8
+const CELL_LEN: usize = 11;
+
+// Sends frames as diffs against the previous one, the first in full
+pub struct FrameSender<W: Write> {
+    out: W,
+    last_frame: Option<Frame>,
+}
+
+impl<W: Write> FrameSender<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            last_frame: None,
+        }
+    }
+}
+
+impl<W: Write> Renderer for FrameSender<W> {
+    fn render(&mut self, frame: &Frame) -> io::Result<RenderStats> {
+        let cols = frame.len();
+        let rows = frame.first().map(|col| col.len()).unwrap_or(0);
+        let last_frame = self
+            .last_frame
+            .as_ref()
+            .filter(|last_frame| last_frame.len() == cols && last_frame[0].len() == rows);
+
+        let mut cells = Vec::new();
+        for (x, col) in frame.iter().enumerate() {
+            for (y, cell) in col.iter().enumerate() {
+                if last_frame.is_none_or(|last_frame| last_frame[x][y] != *cell) {
+                    cells.push((x, y, *cell));
+                }
+            }
+        }
+        let full = last_frame.is_none();
+        if !full && cells.is_empty() {
+            return Ok(RenderStats {
+                frames: 1,
+                ..RenderStats::default()
+            });
+        }
+
+        let mut buffer = Vec::with_capacity(10 + cells.len() * CELL_LEN);
+        buffer.push(FRAME);
+        buffer.extend_from_slice(&(cols as u16).to_be_bytes());
+        buffer.extend_from_slice(&(rows as u16).to_be_bytes());
+        buffer.push(u8::from(full));
+        buffer.extend_from_slice(&(cells.len() as u32).to_be_bytes());
+        for (x, y, cell) in cells.iter() {
+            buffer.extend_from_slice(&(*x as u16).to_be_bytes());
+            buffer.extend_from_slice(&(*y as u16).to_be_bytes());
+            buffer.extend_from_slice(&(cell.glyph as u32).to_be_bytes());
+            buffer.push(color_byte(cell.style.fg));
+            buffer.push(color_byte(cell.style.bg));
+            buffer.push(attributes_byte(cell.style.attrs));
+        }
+        self.out.write_all(&buffer)?;
+        self.out.flush()?;
+
+        self.last_frame = Some(frame.clone());
+        Ok(RenderStats {
+            frames: 1,
+            cells_changed: cells.len(),
+            cells_written: cells.len(),
+            runs: 0,
+            bytes_written: buffer.len(),
+        })
+    }
+
+    // The other side draws the frames at its own size
+    fn resize(&mut self, _term_cols: u16, _term_rows: u16) {}
+}
+
+// Rebuilds the frames of a `FrameSender`
+pub struct FrameReceiver<R: Read> {
+    input: R,
+    frame: Option<Frame>,
+}
+
+impl<R: Read> FrameReceiver<R> {
+    pub fn new(input: R) -> Self {
+        Self { input, frame: None }
+    }
+
+    // Blocks until the next frame, fails with `UnexpectedEof` once the sender is gone
+    pub fn next_frame(&mut self) -> io::Result<&Frame> {
+        let mut header = [0; 10];
+        self.input.read_exact(&mut header)?;
+        if header[0] != FRAME {
+            return Err(invalid_data("unknown message"));
+        }
+        let cols = u16::from_be_bytes([header[1], header[2]]) as usize;
+        let rows = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let full = header[5] != 0;
+        let count = u32::from_be_bytes([header[6], header[7], header[8], header[9]]) as usize;
+        if cols > Playfield::MAX.cols || rows > Playfield::MAX.rows || count > cols * rows {
+            return Err(invalid_data("frame too large"));
+        }
+
+        let mut frame = match self.frame.take() {
+            Some(frame) if !full => frame,
+            _ => frame::new_frame(Playfield { cols, rows }),
+        };
+        if frame.len() != cols || frame.first().map_or(0, |col| col.len()) != rows {
+            return Err(invalid_data("frame size changed without a full frame"));
+        }
+
+        let mut cells = vec![0; count * CELL_LEN];
+        self.input.read_exact(&mut cells)?;
+        for cell in cells.chunks_exact(CELL_LEN) {
+            let x = u16::from_be_bytes([cell[0], cell[1]]) as usize;
+            let y = u16::from_be_bytes([cell[2], cell[3]]) as usize;
+            let glyph = char::from_u32(u32::from_be_bytes([cell[4], cell[5], cell[6], cell[7]]))
+                .ok_or_else(|| invalid_data("invalid glyph"))?;
+            let style = Style {
+                fg: byte_color(cell[8]).ok_or_else(|| invalid_data("invalid color"))?,
+                bg: byte_color(cell[9]).ok_or_else(|| invalid_data("invalid color"))?,
+                attrs: byte_attributes(cell[10]),
+            };
+            let target = frame
+                .get_mut(x)
+                .and_then(|col| col.get_mut(y))
+                .ok_or_else(|| invalid_data("cell outside the frame"))?;
+            *target = Cell::new(glyph, style);
+        }
+
+        Ok(self.frame.insert(frame))
+    }
+}
+
+// A player who connected and said hello, waiting to be welcomed or refused
+pub struct Arrival {
+    stream: TcpStream,
+    pub term_cols: u16,
+    pub term_rows: u16,
+}
+
+impl Arrival {
+    // Reads the hello of someone who just connected, they must be a player saying it in time
+    pub fn greet(mut stream: TcpStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+        let hello = read_line(&mut stream)?;
+        // Commands come whenever the player presses a key
+        stream.set_read_timeout(None)?;
+        let size = hello
+            .strip_prefix(PROTOCOL)
+            .and_then(|rest| rest.strip_prefix(" play "))
+            .and_then(|size| size.split_once(' '))
+            .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)));
+        let Some((term_cols, term_rows)) = size else {
+            let _ = writeln!(stream, "error expected {}", PROTOCOL);
+            return Err(invalid_data("not an invaders player"));
+        };
+
+        Ok(Self {
+            stream,
+            term_cols,
+            term_rows,
+        })
+    }
+
+    pub fn refuse(mut self, reason: &str) -> io::Result<()> {
+        writeln!(self.stream, "error {}", reason)
+    }
+
+    // Starts exchanging commands and frames, each way on its own thread
+    pub fn welcome(mut self) -> io::Result<RemotePlayer> {
+        writeln!(self.stream, "ok")?;
+        self.stream.set_write_timeout(Some(PLAYER_TIMEOUT))?;
+        let mut input = self.stream.try_clone()?;
+        let output = self.stream.try_clone()?;
+
+        let (commands_tx, commands_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut byte = [0];
+            // Ends on disconnection, which the game loop sees as a closed channel
+            while input.read_exact(&mut byte).is_ok() {
+                if let Some(command) = byte_command(byte[0]) {
+                    if commands_tx.send(command).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        // Frames the player has no time for are skipped, a write timing out ends the thread
+        let (frames_tx, frames_rx) = mpsc::sync_channel::<Frame>(1);
+        thread::spawn(move || {
+            let mut sender = FrameSender::new(output);
+            while let Ok(frame) = frames_rx.recv() {
+                if sender.render(&frame).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(RemotePlayer {
+            stream: self.stream,
+            commands: commands_rx,
+            frames: frames_tx,
+        })
+    }
+}
+
+// The host's side of a connected player
+pub struct RemotePlayer {
+    stream: TcpStream,
+    commands: Receiver<Command>,
+    frames: SyncSender<Frame>,
+}
+
+impl RemotePlayer {
+    // Commands received since the last call, fails once the player is gone
+    pub fn commands(&self) -> io::Result<Vec<Command>> {
+        let mut commands = Vec::new();
+        loop {
+            match self.commands.try_recv() {
+                Ok(command) => commands.push(command),
+                Err(TryRecvError::Empty) => return Ok(commands),
+                Err(TryRecvError::Disconnected) => return Err(disconnected()),
+            }
+        }
+    }
+
+    // Fails once the player is gone or stopped taking frames
+    pub fn send_frame(&self, frame: Frame) -> io::Result<()> {
+        match self.frames.try_send(frame) {
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Disconnected(_)) => Err(disconnected()),
+        }
+    }
+}
+
+impl Drop for RemotePlayer {
+    fn drop(&mut self) {
+        // Lets the other side know right away, and ends both threads
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+// Streams the game to any number of spectators, who can come and go while it runs
+pub struct Broadcast {
+    frames: Sender<Frame>,
+}
+
+impl Broadcast {
+    // Accepts spectators on one thread and hands the frames out on another. Each spectator is
+    // written to on a thread of their own, so that a slow one cannot hold up the others.
+    pub fn start(listener: TcpListener) -> Self {
+        let (joined_tx, joined_rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    // Only this connection failed, someone giving up while connecting
+                    Err(err) if is_per_connection(&err) => continue,
+                    // The listener is out of order, the game goes on without new spectators
+                    Err(_) => break,
+                };
+                let joined_tx = joined_tx.clone();
+                thread::spawn(move || serve_spectator(stream, joined_tx));
+            }
+        });
+
+        let (frames_tx, frames_rx) = mpsc::channel::<Frame>();
+        thread::spawn(move || {
+            let mut spectators: Vec<SyncSender<Frame>> = Vec::new();
+            while let Ok(frame) = frames_rx.recv() {
+                spectators.extend(joined_rx.try_iter());
+                // Spectators still busy with the previous frame skip this one
+                spectators.retain(|spectator| {
+                    !matches!(
+                        spectator.try_send(frame.clone()),
+                        Err(TrySendError::Disconnected(_))
+                    )
+                });
+            }
+        });
+
+        Self { frames: frames_tx }
+    }
+
+    pub fn send_frame(&self, frame: Frame) {
+        let _ = self.frames.send(frame);
+    }
+}
+
+fn is_per_connection(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::Interrupted
+    )
+}
+
+// Sends frames to one spectator until they leave or fall behind by more than the timeout
+fn serve_spectator(mut stream: TcpStream, joined: Sender<SyncSender<Frame>>) {
+    if welcome_spectator(&mut stream).is_err() {
+        return;
+    }
+    let (frames_tx, frames_rx) = mpsc::sync_channel(1);
+    if joined.send(frames_tx).is_err() {
+        return;
+    }
+    // Late joiners get a full frame first, as their sender has none to diff against
+    let mut sender = FrameSender::new(stream);
+    for frame in frames_rx {
+        if sender.render(&frame).is_err() {
+            break;
+        }
+    }
+}
+
+fn welcome_spectator(stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(SPECTATOR_TIMEOUT))?;
+    stream.set_write_timeout(Some(SPECTATOR_TIMEOUT))?;
+    if read_line(stream)? != format!("{} watch", PROTOCOL) {
+        writeln!(stream, "error expected {} watch", PROTOCOL)?;
+        return Err(invalid_data("not an invaders spectator"));
+    }
+    writeln!(stream, "ok")
+}
+
+// A player's or spectator's connection to a game running elsewhere
+pub struct HostConnection {
+    stream: TcpStream,
+}
+
+impl HostConnection {
+    // Returns the connection, to send commands, and the frames the host will stream
+    pub fn connect(
+        addr: impl ToSocketAddrs,
+        term_cols: u16,
+        term_rows: u16,
+    ) -> io::Result<(Self, FrameReceiver<TcpStream>)> {
+        let hello = format!("{} play {} {}", PROTOCOL, term_cols, term_rows);
+        Self::open(addr, &hello)
+    }
+
+    // Only receives frames, from a game started with a broadcast
+    pub fn watch(addr: impl ToSocketAddrs) -> io::Result<(Self, FrameReceiver<TcpStream>)> {
+        Self::open(addr, &format!("{} watch", PROTOCOL))
+    }
+
+    fn open(addr: impl ToSocketAddrs, hello: &str) -> io::Result<(Self, FrameReceiver<TcpStream>)> {
+        let mut stream = TcpStream::connect(addr)?;
+        writeln!(stream, "{}", hello)?;
+        let reply = read_line(&mut stream)?;
+        if let Some(reason) = reply.strip_prefix("error ") {
+            return Err(io::Error::other(format!("refused by the host: {}", reason)));
+        }
+        if reply != "ok" {
+            return Err(invalid_data("not an invaders game"));
+        }
+
+        let frames = FrameReceiver::new(stream.try_clone()?);
+        Ok((Self { stream }, frames))
+    }
+
+    pub fn send(&mut self, command: Command) -> io::Result<()> {
+        self.stream.write_all(&[command_byte(command)])
+    }
+}
+
+impl Drop for HostConnection {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+// Read byte by byte, so that nothing sent after the line gets buffered away
+fn read_line(stream: &mut TcpStream) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    loop {
+        stream.read_exact(&mut byte)?;
+        match byte[0] {
+            b'\n' => break,
+            _ if line.len() == MAX_LINE_LEN => return Err(invalid_data("line too long")),
+            b => line.push(b),
+        }
+    }
+    String::from_utf8(line)
+        .map(|line| line.trim_end().to_string())
+        .map_err(|_| invalid_data("line is not UTF-8"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "the other player left")
+}
+
+fn command_byte(command: Command) -> u8 {
+    match command {
+        Command::MoveLeft => b'L',
+        Command::MoveRight => b'R',
+        Command::Shoot => b'S',
+    }
+}
+
+fn byte_command(byte: u8) -> Option<Command> {
+    match byte {
+        b'L' => Some(Command::MoveLeft),
+        b'R' => Some(Command::MoveRight),
+        b'S' => Some(Command::Shoot),
+        _ => None,
+    }
+}
+
+const COLORS: [Color; 9] = [
+    Color::Black,
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+fn color_byte(color: Color) -> u8 {
+    COLORS.iter().position(|c| *c == color).unwrap_or(0) as u8
+}
+
+fn byte_color(byte: u8) -> Option<Color> {
+    COLORS.get(byte as usize).copied()
+}
+
+fn attributes_byte(attrs: Attributes) -> u8 {
+    u8::from(attrs.bold) | u8::from(attrs.dim) << 1 | u8::from(attrs.reverse) << 2
+}
+
+fn byte_attributes(byte: u8) -> Attributes {
+    Attributes {
+        bold: byte & 1 != 0,
+        dim: byte & 2 != 0,
+        reverse: byte & 4 != 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn frame_with(x: usize, y: usize, cell: Cell) -> Frame {
+        let mut frame = frame::new_frame(Playfield { cols: 12, rows: 4 });
+        frame[x][y] = cell;
+        frame
+    }
+
+    // Every cell differs from the frame before, so that each one is sent in full
+    fn changing_frame(i: usize) -> Frame {
+        let glyph = if i.is_multiple_of(2) { 'x' } else { 'o' };
+        let mut frame = frame::new_frame(Playfield::MAX);
+        for cell in frame.iter_mut().flatten() {
+            *cell = Cell::new(glyph, Style::fg(Color::White));
+        }
+        frame
+    }
+
+    fn listen() -> (TcpListener, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        (listener, addr)
+    }
+
+    #[test]
+    fn frames_are_rebuilt_from_their_diffs() {
+        let first = frame_with(0, 0, Cell::new('A', Style::fg(Color::Green).bold()));
+        let mut second = first.clone();
+        second[11][3] = Cell::new('\u{2580}', Style::fg(Color::Red).reverse());
+        let resized = frame::new_frame(Playfield { cols: 5, rows: 2 });
+
+        let mut bytes = Vec::new();
+        let mut sender = FrameSender::new(&mut bytes);
+        for frame in [&first, &second, &resized] {
+            sender.render(frame).unwrap();
+        }
+
+        let mut receiver = FrameReceiver::new(&bytes[..]);
+        assert_eq!(receiver.next_frame().unwrap(), &first);
+        assert_eq!(receiver.next_frame().unwrap(), &second);
+        assert_eq!(receiver.next_frame().unwrap(), &resized);
+        let end = receiver.next_frame().unwrap_err();
+        assert_eq!(end.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn a_joining_player_sends_commands_and_gets_frames() {
+        let (listener, addr) = listen();
+        let frame = frame_with(3, 2, Cell::new('@', Style::fg(Color::Cyan)));
+        // The player stays until the host read the commands, leaving reports an error instead
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let player = thread::spawn(move || {
+            let (mut host, mut frames) = HostConnection::connect(addr, 50, 25).unwrap();
+            host.send(Command::Shoot).unwrap();
+            host.send(Command::MoveLeft).unwrap();
+            let frame = frames.next_frame().unwrap().clone();
+            let _ = done_rx.recv();
+            frame
+        });
+
+        let arrival = Arrival::greet(listener.accept().unwrap().0).unwrap();
+        assert_eq!((arrival.term_cols, arrival.term_rows), (50, 25));
+        let remote = arrival.welcome().unwrap();
+        remote.send_frame(frame.clone()).unwrap();
+
+        let mut commands = Vec::new();
+        let start = Instant::now();
+        while commands.len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            commands.extend(remote.commands().unwrap());
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(commands, [Command::Shoot, Command::MoveLeft]);
+        drop(done_tx);
+        assert_eq!(player.join().unwrap(), frame);
+
+        // The host sees the player leave
+        let start = Instant::now();
+        while remote.commands().is_ok() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(remote.commands().is_err());
+    }
+
+    #[test]
+    fn a_player_who_stops_taking_frames_is_dropped() {
+        let (listener, addr) = listen();
+        // Never reads, so the host cannot write to it once the socket buffers are full
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let player = thread::spawn(move || {
+            let connection = HostConnection::connect(addr, 80, 40).unwrap();
+            let _ = done_rx.recv();
+            drop(connection);
+        });
+        let arrival = Arrival::greet(listener.accept().unwrap().0).unwrap();
+        let remote = arrival.welcome().unwrap();
+
+        let start = Instant::now();
+        let mut sent = 0;
+        while remote.send_frame(changing_frame(sent)).is_ok() {
+            assert!(start.elapsed() < Duration::from_secs(30), "still sending");
+            sent += 1;
+        }
+        drop(done_tx);
+        player.join().unwrap();
+    }
+
+    #[test]
+    fn a_refused_player_is_told_why() {
+        let (listener, addr) = listen();
+        let player = thread::spawn(move || HostConnection::connect(addr, 10, 10).err());
+        let arrival = Arrival::greet(listener.accept().unwrap().0).unwrap();
+        arrival.refuse("terminal too small").unwrap();
+        let err = player.join().unwrap().expect("the player was refused");
+        assert!(err.to_string().contains("terminal too small"), "{}", err);
+    }
+
+    #[test]
+    fn a_silent_connection_does_not_block_the_host() {
+        let (listener, addr) = listen();
+        let _silent = TcpStream::connect(addr).unwrap();
+        let start = Instant::now();
+        assert!(Arrival::greet(listener.accept().unwrap().0).is_err());
+        assert!(start.elapsed() < HELLO_TIMEOUT * 2);
+    }
+
+    #[test]
+    fn a_stalled_spectator_does_not_hold_up_the_others() {
+        let (listener, addr) = listen();
+        let broadcast = Broadcast::start(listener);
+        // Never reads, so the host cannot write to it once the socket buffers are full
+        let (_stalled, _) = HostConnection::watch(&addr).unwrap();
+        let (_watcher, mut frames) = HostConnection::watch(&addr).unwrap();
+        // Longest wait between two frames, the write timeout if the stalled one held the others up
+        let watcher = thread::spawn(move || {
+            let mut longest = Duration::ZERO;
+            let mut last = Instant::now();
+            while frames.next_frame().unwrap()[0][0].glyph != '!' {
+                longest = longest.max(last.elapsed());
+                last = Instant::now();
+            }
+            longest
+        });
+
+        // Every cell changes from one frame to the next, several megabytes in all
+        for i in 0..500 {
+            broadcast.send_frame(changing_frame(i));
+            thread::sleep(Duration::from_millis(1));
+        }
+        let start = Instant::now();
+        while !watcher.is_finished() && start.elapsed() < Duration::from_secs(10) {
+            broadcast.send_frame(frame_with(0, 0, Cell::new('!', Style::fg(Color::Red))));
+            thread::sleep(Duration::from_millis(10));
+        }
+        let longest = watcher.join().unwrap();
+        assert!(longest < SPECTATOR_TIMEOUT / 2, "{:?}", longest);
+    }
+
+    #[test]
+    fn strangers_are_turned_away() {
+        let (listener, addr) = listen();
+        let mut stranger = TcpStream::connect(addr).unwrap();
+        writeln!(stranger, "GET / HTTP/1.1").unwrap();
+        assert!(Arrival::greet(listener.accept().unwrap().0).is_err());
+        assert!(read_line(&mut stranger).unwrap().starts_with("error"));
+    }
+}
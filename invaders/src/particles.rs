@@ -0,0 +1,149 @@
+use crate::frame::{Cell, Color, Drawable, Frame, Layer, Style};
+use crate::playfield::Playfield;
+use crate::rng::Rng;
+use std::time::Duration;
+
+// Particles alive at once, bursts past this are cut short so frames stay cheap
+pub const MAX_PARTICLES: usize = 200;
+// Cells per second squared, pulling debris down
+const GRAVITY: f32 = 20.0;
+const GLYPHS: [char; 5] = ['.', ',', '\'', '`', '*'];
+
+// A bit of debris flying off, in cells and cells per second
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    glyph: char,
+    color: Color,
+    life: Duration,
+}
+
+// Cosmetic only, with its own generator so that it never changes how a game goes
+pub struct Particles {
+    particles: Vec<Particle>,
+    playfield: Playfield,
+    rng: Rng,
+}
+
+impl Particles {
+    pub fn new(playfield: Playfield, seed: u64) -> Self {
+        Self {
+            particles: Vec::new(),
+            playfield,
+            rng: Rng::new(seed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    // Debris flying out of the given cell, mostly upwards
+    pub fn burst(&mut self, x: usize, y: usize, count: usize, color: Color) {
+        let count = count.min(MAX_PARTICLES - self.particles.len());
+        for _ in 0..count {
+            let particle = Particle {
+                x: x as f32 + 0.5,
+                y: y as f32 + 0.5,
+                vx: self.random(-10.0, 10.0),
+                vy: self.random(-8.0, 2.0),
+                glyph: GLYPHS[self.rng.below(GLYPHS.len())],
+                color,
+                life: Duration::from_millis(self.random(300.0, 700.0) as u64),
+            };
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        let secs = delta.as_secs_f32();
+        for particle in self.particles.iter_mut() {
+            particle.x += particle.vx * secs;
+            particle.y += particle.vy * secs;
+            particle.vy += GRAVITY * secs;
+            particle.life = particle.life.saturating_sub(delta);
+        }
+
+        // Debris stays out of the row of the power-up timers
+        let (cols, rows) = (
+            self.playfield.cols as f32,
+            self.playfield.bottom() as f32 + 1.0,
+        );
+        self.particles.retain(|particle| {
+            !particle.life.is_zero()
+                && (0.0..cols).contains(&particle.x)
+                && (0.0..rows).contains(&particle.y)
+        });
+    }
+
+    fn random(&mut self, low: f32, high: f32) -> f32 {
+        low + (high - low) * (self.rng.below(1000) as f32 / 1000.0)
+    }
+}
+
+impl Drawable for Particles {
+    fn draw(&self, frame: &mut Frame) {
+        for particle in self.particles.iter() {
+            let (x, y) = (particle.x as usize, particle.y as usize);
+            frame[x][y] = Cell::new(particle.glyph, Style::fg(particle.color));
+        }
+    }
+
+    // Particles fall half a row at a time
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        for particle in self.particles.iter() {
+            let (x, y) = (particle.x as usize, (particle.y * 2.0) as usize);
+            pixels[x][y] = Cell::new(particle.glyph, Style::fg(particle.color));
+        }
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Effects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_stop_at_the_cap() {
+        let mut particles = Particles::new(Playfield::MIN, 7);
+        particles.burst(20, 10, MAX_PARTICLES - 5, Color::Red);
+        particles.burst(20, 10, 30, Color::Red);
+        assert_eq!(particles.len(), MAX_PARTICLES);
+        particles.burst(20, 10, 30, Color::Red);
+        assert_eq!(particles.len(), MAX_PARTICLES);
+    }
+
+    #[test]
+    fn particles_expire_with_their_lifetime() {
+        let mut particles = Particles::new(Playfield::MIN, 7);
+        particles.burst(20, 10, 50, Color::Red);
+        // Shorter than any lifetime, and too short to leave the playfield from its middle
+        particles.update(Duration::from_millis(100));
+        assert_eq!(particles.len(), 50);
+        particles.update(Duration::from_millis(600));
+        assert!(particles.is_empty());
+    }
+
+    #[test]
+    fn particles_leaving_the_game_are_gone() {
+        let playfield = Playfield::MIN;
+        let mut particles = Particles::new(playfield, 7);
+        particles.burst(0, 0, 50, Color::Red);
+        particles.update(Duration::from_millis(250));
+        assert!(particles.len() < 50);
+        assert!(!particles.is_empty());
+        for particle in particles.particles.iter() {
+            assert!((0.0..playfield.cols as f32).contains(&particle.x));
+            assert!((0.0..playfield.bottom() as f32 + 1.0).contains(&particle.y));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "invaders";
+
+// Per-user directory for the game's persistent data, following each platform's convention
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".local").join("share")))
+    };
+
+    base.map(|base| base.join(APP_DIR))
+}
+
+// Per-user directory for settings the player edits by hand
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home_dir().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|home| home.join(".config")))
+    };
+
+    base.map(|base| base.join(APP_DIR))
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
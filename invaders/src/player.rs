@@ -1,73 +1,254 @@
-use crate::frame::{Drawable, Frame};
-use crate::invaders::Invaders;
-use crate::shot::Shot;
-use crate::{NUM_COLS, NUM_ROWS};
+use crate::animation::{Animation, Repeat};
+use crate::bunkers::Bunkers;
+use crate::frame::{self, Cell, Color, Drawable, Frame, Style};
+use crate::invaders::{InvaderHit, Invaders};
+use crate::playfield::Playfield;
+use crate::powerups::{Effect, PowerUpKind};
+use crate::shot::{Direction, Shot};
+use crate::sprite::{Rect, Sprite};
+use crate::DEFAULT_LIVES;
 use std::time::Duration;
 
+// A cannon three columns wide, positioned by its middle column where the shots come from
 pub struct Player {
+    // 0 for the first player, 1 for the second
+    number: usize,
     x: usize,
     y: usize,
+    playfield: Playfield,
     shots: Vec<Shot>,
+    lives: u32,
+    score: u32,
+    // Collected power-ups still running
+    effects: Vec<Effect>,
+    // Shown instead of the player for a moment after being hit
+    dying: Option<Animation<char>>,
 }
 
 impl Player {
-    pub fn new() -> Self {
+    pub fn new(lives: u32, playfield: Playfield) -> Self {
+        Self::numbered(0, 1, lives, playfield)
+    }
+
+    // One of several players, spread evenly along the bottom row
+    pub fn numbered(number: usize, players: usize, lives: u32, playfield: Playfield) -> Self {
         Self {
-            x: NUM_COLS / 2,
-            y: NUM_ROWS - 1,
+            number,
+            x: (playfield.cols * (number + 1) / (players + 1)).clamp(1, playfield.cols - 2),
+            y: playfield.bottom(),
+            playfield,
             shots: Vec::new(),
+            lives,
+            score: 0,
+            effects: Vec::new(),
+            dying: None,
         }
     }
 
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    fn sprite(&self) -> Sprite {
+        match self.number {
+            0 => Sprite::new(&["/A\\"]),
+            _ => Sprite::new(&["/^\\"]),
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        self.sprite().bounds(self.x - 1, self.y)
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn dead(&self) -> bool {
+        self.lives == 0
+    }
+
+    pub fn effects(&self) -> &[Effect] {
+        &self.effects
+    }
+
+    pub fn has(&self, kind: PowerUpKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    // Collecting a power-up that is already running starts it over
+    pub fn collect(&mut self, kind: PowerUpKind) {
+        self.effects.retain(|effect| effect.kind != kind);
+        self.effects.push(Effect::new(kind));
+    }
+
     pub fn move_left(&mut self) {
-        if self.x > 0 {
+        if !self.dead() && self.x > 1 {
             self.x -= 1;
         }
     }
 
     pub fn move_right(&mut self) {
-        if self.x < NUM_COLS - 1 {
+        if !self.dead() && self.x < self.playfield.cols - 2 {
             self.x += 1;
         }
     }
 
     pub fn shoot(&mut self) -> bool {
-        if self.shots.len() < 2 {
-            self.shots.push(Shot::new(self.x, self.y - 1));
-            true
+        // The limit counts volleys, a spread volley is three shots side by side
+        let volleys = if self.has(PowerUpKind::RapidFire) {
+            5
         } else {
-            false
+            2
+        };
+        let xs: Vec<usize> = if self.has(PowerUpKind::Spread) {
+            (self.x.saturating_sub(1)..=self.x + 1)
+                .filter(|&x| x < self.playfield.cols)
+                .collect()
+        } else {
+            vec![self.x]
+        };
+        let width = if self.has(PowerUpKind::Spread) { 3 } else { 1 };
+        if self.dead() || self.shots.len() + width > volleys * width {
+            return false;
         }
+
+        for x in xs {
+            let mut shot = Shot::new(x, self.y - 1, Direction::Up, self.playfield);
+            shot.piercing = self.has(PowerUpKind::Piercing);
+            self.shots.push(shot);
+        }
+        true
     }
 
     pub fn update(&mut self, delta: Duration) {
         for shot in self.shots.iter_mut() {
             shot.update(delta);
         }
+        for effect in self.effects.iter_mut() {
+            effect.update(delta);
+        }
+        if let Some(dying) = self.dying.as_mut() {
+            dying.update(delta);
+            if dying.finished() {
+                self.dying = None;
+            }
+        }
 
         self.shots.retain(|shot| !shot.dead());
+        self.effects.retain(|effect| !effect.finished());
     }
 
-    pub fn detect_hits(&mut self, invaders: &mut Invaders) -> bool {
-        let mut hit_something = false;
+    // Reports the invaders hit by the shots, scoring their points when killed
+    pub fn detect_hits(
+        &mut self,
+        invaders: &mut Invaders,
+        bunkers: &mut Bunkers,
+    ) -> Vec<InvaderHit> {
+        let mut hits = Vec::new();
         for shot in self.shots.iter_mut() {
-            if !shot.exploding {
-                if invaders.kill_invader_at(shot.x, shot.y) {
-                    hit_something = true;
+            if shot.exploding {
+                continue;
+            }
+            // Bunkers stop the shots of both sides
+            if bunkers.hit_at(shot.x, shot.y) {
+                shot.explode();
+                continue;
+            }
+            // A shot takes a few ticks to leave a cell, and may go on into the next row of a large
+            // invader
+            let id = invaders.invader_at(shot.x, shot.y);
+            if shot.piercing && id.is_some_and(|id| shot.pierced.contains(&id)) {
+                continue;
+            }
+            if let Some(hit) = invaders.kill_invader_at(shot.x, shot.y) {
+                if !shot.piercing {
                     shot.explode();
-                    break;
                 }
+                shot.pierced.extend(id);
+                self.score += hit.points;
+                hits.push(hit);
             }
         }
-        hit_something
+        hits
+    }
+
+    // Loses a life if one of the invaders' bombs reached the cannon, unless shielded
+    pub fn detect_bombs(&mut self, invaders: &mut Invaders) -> bool {
+        if self.lives == 0 {
+            return false;
+        }
+        let left = self.x - 1;
+        let mut hit = false;
+        for (dx, dy, _) in self.sprite().cells() {
+            hit |= invaders.bomb_at(left + dx, self.y + dy);
+        }
+        if hit && !self.has(PowerUpKind::Shield) {
+            self.lives -= 1;
+            self.dying = Some(Animation::even(
+                &['*', 'X', '+', '.'],
+                Duration::from_millis(120),
+                Repeat::Once,
+            ));
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new(DEFAULT_LIVES, Playfield::default())
+    }
+}
+
+impl Player {
+    fn draw_sprite(&self, frame: &mut Frame) {
+        if let Some(dying) = self.dying.as_ref() {
+            frame[self.x][self.y] = Cell::new(dying.current(), Style::fg(Color::Red).bold());
+            return;
+        }
+        // Players out of lives leave, their last shots keep going
+        if !self.dead() {
+            let color = match self.number {
+                0 => Color::Green,
+                _ => Color::Cyan,
+            };
+            let style = if self.has(PowerUpKind::Shield) {
+                Style::fg(color).bold().reverse()
+            } else {
+                Style::fg(color).bold()
+            };
+            self.sprite().draw(frame, self.x - 1, self.y, style);
+        }
     }
 }
 
 impl Drawable for Player {
     fn draw(&self, frame: &mut Frame) {
-        frame[self.x][self.y] = "A";
+        self.draw_sprite(frame);
         for shot in self.shots.iter() {
             shot.draw(frame);
         }
     }
+
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        frame::draw_doubled(pixels, |frame| self.draw_sprite(frame));
+        for shot in self.shots.iter() {
+            shot.draw_half_blocks(pixels);
+        }
+    }
 }
@@ -0,0 +1,41 @@
+// Size of the game area in terminal cells, chosen when a game starts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Playfield {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl Playfield {
+    // Smallest playfield the levels and bunkers are designed for
+    pub const MIN: Playfield = Playfield { cols: 40, rows: 20 };
+    // Past this the army takes ages to cross and the player is out of reach of the bombs
+    pub const MAX: Playfield = Playfield { cols: 64, rows: 30 };
+
+    // Largest playfield fitting in a terminal of the given size, if it is big enough at all
+    pub fn fit(term_cols: u16, term_rows: u16) -> Option<Self> {
+        let (cols, rows) = (term_cols as usize, term_rows as usize);
+        if cols < Self::MIN.cols || rows < Self::MIN.rows {
+            return None;
+        }
+
+        Some(Self {
+            cols: cols.min(Self::MAX.cols),
+            rows: rows.min(Self::MAX.rows),
+        })
+    }
+
+    // Lowest row the game uses, the last row of the frame holds the running power-ups
+    pub fn bottom(&self) -> usize {
+        self.rows - 2
+    }
+
+    pub fn fits_in(&self, term_cols: u16, term_rows: u16) -> bool {
+        self.cols <= term_cols as usize && self.rows <= term_rows as usize
+    }
+}
+
+impl Default for Playfield {
+    fn default() -> Self {
+        Self::MIN
+    }
+}
@@ -0,0 +1,140 @@
+use crate::frame::{Cell, Color, Drawable, Frame, Style};
+use crate::playfield::Playfield;
+use crate::shot::{self, Direction};
+use rusty_time::Timer;
+use std::time::Duration;
+
+// One kill in this many drops a power-up
+pub const DROP_CHANCE: usize = 12;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerUpKind {
+    // More shots on screen at once
+    RapidFire,
+    // Three shots side by side
+    Spread,
+    // Shots go through invaders
+    Piercing,
+    // Bombs do no harm
+    Shield,
+}
+
+impl PowerUpKind {
+    pub const ALL: [PowerUpKind; 4] = [
+        PowerUpKind::RapidFire,
+        PowerUpKind::Spread,
+        PowerUpKind::Piercing,
+        PowerUpKind::Shield,
+    ];
+
+    pub fn glyph(self) -> char {
+        match self {
+            PowerUpKind::RapidFire => 'F',
+            PowerUpKind::Spread => 'W',
+            PowerUpKind::Piercing => 'L',
+            PowerUpKind::Shield => 'S',
+        }
+    }
+
+    // Shown in the HUD while active
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::RapidFire => "RAPID",
+            PowerUpKind::Spread => "SPREAD",
+            PowerUpKind::Piercing => "LASER",
+            PowerUpKind::Shield => "SHIELD",
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        match self {
+            PowerUpKind::RapidFire | PowerUpKind::Spread => Duration::from_secs(10),
+            PowerUpKind::Piercing => Duration::from_secs(8),
+            PowerUpKind::Shield => Duration::from_secs(6),
+        }
+    }
+}
+
+// Falls from where an invader was killed until a player touches it or it reaches the bottom
+pub struct PowerUp {
+    pub x: usize,
+    pub y: usize,
+    pub kind: PowerUpKind,
+    bottom: usize,
+    timer: Timer,
+    gone: bool,
+}
+
+impl PowerUp {
+    pub fn new(x: usize, y: usize, kind: PowerUpKind, playfield: Playfield) -> Self {
+        Self {
+            x,
+            y,
+            kind,
+            bottom: playfield.bottom(),
+            timer: Timer::new(Duration::from_millis(150)),
+            gone: false,
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+        if self.timer.finished() {
+            self.timer.reset();
+            if self.y < self.bottom {
+                self.y += 1;
+            } else {
+                self.gone = true;
+            }
+        }
+    }
+
+    // Missed by every player, it stays on the bottom row for one step first
+    pub fn dead(&self) -> bool {
+        self.gone
+    }
+}
+
+impl PowerUp {
+    fn cell(&self) -> Cell {
+        Cell::new(self.kind.glyph(), Style::fg(Color::Magenta).bold())
+    }
+}
+
+impl Drawable for PowerUp {
+    fn draw(&self, frame: &mut Frame) {
+        frame[self.x][self.y] = self.cell();
+    }
+
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        let row = shot::half_row(self.y, Direction::Down, self.timer.percent());
+        pixels[self.x][row] = self.cell();
+    }
+}
+
+// A collected power-up and the time it has left
+pub struct Effect {
+    pub kind: PowerUpKind,
+    timer: Timer,
+}
+
+impl Effect {
+    pub fn new(kind: PowerUpKind) -> Self {
+        Self {
+            kind,
+            timer: Timer::new(kind.duration()),
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.timer.tick(delta);
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.timer.remaining()
+    }
+
+    pub fn finished(&self) -> bool {
+        self.timer.finished()
+    }
+}
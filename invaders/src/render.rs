@@ -1,24 +1,223 @@
+mod memory;
+mod terminal;
+
+pub use memory::MemoryRenderer;
+pub use terminal::TerminalRenderer;
+
 use crate::frame::Frame;
-use crossterm::cursor::MoveTo;
-use crossterm::style::{Color, SetBackgroundColor};
-use crossterm::terminal::{Clear, ClearType};
-use crossterm::QueueableCommand;
-use std::io::{Stdout, Write};
-
-pub fn render(stdout: &mut Stdout, last_frame: &Frame, curr_frame: &Frame, force: bool) {
-    if force {
-        stdout.queue(SetBackgroundColor(Color::Blue)).unwrap();
-        stdout.queue(Clear(ClearType::All)).unwrap();
-        stdout.queue(SetBackgroundColor(Color::Black)).unwrap();
-    }
-
-    for (x, col) in curr_frame.iter().enumerate() {
-        for (y, s) in col.iter().enumerate() {
-            if *s != last_frame[x][y] || force {
-                stdout.queue(MoveTo(x as u16, y as u16)).unwrap();
-                print!("{}", *s);
+use std::io;
+use std::ops::AddAssign;
+
+// Unchanged cells between two changed ones are rewritten when that is cheaper than moving the cursor
+const MAX_GAP: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMode {
+    Color,
+    // Only attributes, for terminals without colors
+    Monochrome,
+}
+
+// What drawing a frame cost, to measure the rendering on slow links
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub frames: usize,
+    pub cells_changed: usize,
+    // Changed cells plus the unchanged ones rewritten to bridge small gaps
+    pub cells_written: usize,
+    // Horizontal runs of cells, each costing one cursor move
+    pub runs: usize,
+    pub bytes_written: usize,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.frames += other.frames;
+        self.cells_changed += other.cells_changed;
+        self.cells_written += other.cells_written;
+        self.runs += other.runs;
+        self.bytes_written += other.bytes_written;
+    }
+}
+
+// Displays frames, centered in a terminal of a given size
+pub trait Renderer {
+    // Draws what changed since the previous frame, or everything after a resize
+    fn render(&mut self, frame: &Frame) -> io::Result<RenderStats>;
+
+    // The next frame is drawn in full
+    fn resize(&mut self, term_cols: u16, term_rows: u16);
+}
+
+// Top left corner of the frame once centered in the terminal, if it fits
+pub fn origin(frame: &Frame, term_cols: u16, term_rows: u16) -> Option<(u16, u16)> {
+    let (cols, rows) = frame_size(frame);
+    let (cols, rows) = (cols as u16, rows as u16);
+    if cols > term_cols || rows > term_rows {
+        return None;
+    }
+    Some(((term_cols - cols) / 2, (term_rows - rows) / 2))
+}
+
+pub fn too_small_message(frame: &Frame) -> String {
+    let (cols, rows) = frame_size(frame);
+    format!("Terminal too small, needs {}x{}", cols, rows)
+}
+
+fn frame_size(frame: &Frame) -> (usize, usize) {
+    (frame.len(), frame.first().map(|col| col.len()).unwrap_or(0))
+}
+
+// Horizontal stretch of cells to write, in frame coordinates
+#[derive(Debug, PartialEq, Eq)]
+struct Run {
+    x: usize,
+    y: usize,
+    len: usize,
+}
+
+// Runs covering every cell that differs from the last frame, or the whole frame without one
+fn changed_runs(
+    last_frame: Option<&Frame>,
+    curr_frame: &Frame,
+    stats: &mut RenderStats,
+) -> Vec<Run> {
+    let (cols, rows) = frame_size(curr_frame);
+    let last_frame = last_frame.filter(|last_frame| frame_size(last_frame) == (cols, rows));
+    let changed = |x: usize, y: usize| {
+        last_frame.is_none_or(|last_frame| curr_frame[x][y] != last_frame[x][y])
+    };
+
+    let mut runs = Vec::new();
+    for y in 0..rows {
+        let mut x = 0;
+        while x < cols {
+            if !changed(x, y) {
+                x += 1;
+                continue;
             }
+
+            // Extend the run over changed cells and small gaps followed by more changes
+            let mut end = x;
+            while end < cols {
+                if changed(end, y) {
+                    stats.cells_changed += 1;
+                    end += 1;
+                    continue;
+                }
+                let gap = (end..cols).take_while(|gap_x| !changed(*gap_x, y)).count();
+                if gap <= MAX_GAP && end + gap < cols {
+                    end += gap;
+                } else {
+                    break;
+                }
+            }
+
+            stats.cells_written += end - x;
+            runs.push(Run { x, y, len: end - x });
+            x = end;
+        }
+    }
+
+    stats.runs += runs.len();
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{self, Cell, Style};
+    use crate::playfield::Playfield;
+
+    fn blank(cols: usize, rows: usize) -> Frame {
+        frame::new_frame(Playfield { cols, rows })
+    }
+
+    fn mark(frame: &mut Frame, xs: &[usize], y: usize) {
+        for &x in xs {
+            frame[x][y] = Cell::new('#', Style::DEFAULT);
         }
     }
-    stdout.flush().unwrap();
+
+    #[test]
+    fn everything_is_written_without_a_last_frame() {
+        let mut stats = RenderStats::default();
+        let runs = changed_runs(None, &blank(10, 3), &mut stats);
+        assert_eq!(
+            runs,
+            (0..3).map(|y| Run { x: 0, y, len: 10 }).collect::<Vec<_>>()
+        );
+        assert_eq!(stats.cells_changed, 30);
+        assert_eq!(stats.cells_written, 30);
+        assert_eq!(stats.runs, 3);
+    }
+
+    #[test]
+    fn nothing_is_written_when_nothing_changed() {
+        let mut stats = RenderStats::default();
+        let last = blank(10, 3);
+        assert!(changed_runs(Some(&last), &last.clone(), &mut stats).is_empty());
+        assert_eq!(stats, RenderStats::default());
+    }
+
+    #[test]
+    fn gaps_up_to_max_gap_are_bridged() {
+        let last = blank(20, 2);
+        let mut curr = last.clone();
+        // Three unchanged cells in between, bridged
+        mark(&mut curr, &[1, 2 + MAX_GAP], 0);
+        // One more, two runs
+        mark(&mut curr, &[1, 3 + MAX_GAP], 1);
+
+        let mut stats = RenderStats::default();
+        let runs = changed_runs(Some(&last), &curr, &mut stats);
+        assert_eq!(
+            runs,
+            [
+                Run {
+                    x: 1,
+                    y: 0,
+                    len: MAX_GAP + 2
+                },
+                Run { x: 1, y: 1, len: 1 },
+                Run {
+                    x: 3 + MAX_GAP,
+                    y: 1,
+                    len: 1
+                },
+            ]
+        );
+        assert_eq!(stats.cells_changed, 4);
+        assert_eq!(stats.cells_written, MAX_GAP + 4);
+        assert_eq!(stats.runs, 3);
+    }
+
+    #[test]
+    fn runs_do_not_extend_over_the_unchanged_end_of_a_row() {
+        let last = blank(10, 1);
+        let mut curr = last.clone();
+        mark(&mut curr, &[7], 0);
+        let mut stats = RenderStats::default();
+        assert_eq!(
+            changed_runs(Some(&last), &curr, &mut stats),
+            [Run { x: 7, y: 0, len: 1 }]
+        );
+    }
+
+    #[test]
+    fn a_last_frame_of_another_size_is_ignored() {
+        let mut stats = RenderStats::default();
+        let runs = changed_runs(Some(&blank(8, 2)), &blank(10, 3), &mut stats);
+        assert_eq!(runs.len(), 3);
+        assert_eq!(stats.cells_written, 30);
+    }
+
+    #[test]
+    fn frames_are_centered_when_they_fit() {
+        let frame = blank(10, 4);
+        assert_eq!(origin(&frame, 20, 10), Some((5, 3)));
+        assert_eq!(origin(&frame, 10, 4), Some((0, 0)));
+        assert_eq!(origin(&frame, 9, 10), None);
+        assert_eq!(too_small_message(&frame), "Terminal too small, needs 10x4");
+    }
 }
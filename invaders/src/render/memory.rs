@@ -0,0 +1,168 @@
+use super::{changed_runs, origin, too_small_message, ColorMode, RenderStats, Renderer};
+use crate::frame::{Cell, Color, Frame, Style};
+use std::io;
+
+// Keeps what a terminal of the given size would display, for tests and other headless uses
+pub struct MemoryRenderer {
+    mode: ColorMode,
+    // Indexed [x][y] like frames, covering the whole terminal
+    screen: Frame,
+    last_frame: Option<Frame>,
+}
+
+impl MemoryRenderer {
+    pub fn new(term_cols: u16, term_rows: u16, mode: ColorMode) -> Self {
+        Self {
+            mode,
+            screen: vec![vec![Cell::BLANK; term_rows as usize]; term_cols as usize],
+            last_frame: None,
+        }
+    }
+
+    pub fn screen(&self) -> &Frame {
+        &self.screen
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<Cell> {
+        self.screen.get(x).and_then(|col| col.get(y)).copied()
+    }
+
+    // One line of the screen, glyphs only
+    pub fn line(&self, y: usize) -> String {
+        self.screen
+            .iter()
+            .map(|col| col.get(y).map_or(' ', |cell| cell.glyph))
+            .collect()
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        let rows = self.screen.first().map(|col| col.len()).unwrap_or(0);
+        (0..rows).map(|y| self.line(y)).collect()
+    }
+
+    fn clear(&mut self, background: Cell) {
+        for col in self.screen.iter_mut() {
+            col.fill(background);
+        }
+    }
+}
+
+impl Renderer for MemoryRenderer {
+    fn render(&mut self, frame: &Frame) -> io::Result<RenderStats> {
+        let mut stats = RenderStats {
+            frames: 1,
+            ..RenderStats::default()
+        };
+        let term_cols = self.screen.len() as u16;
+        let term_rows = self.screen.first().map(|col| col.len()).unwrap_or(0) as u16;
+
+        let Some((left, top)) = origin(frame, term_cols, term_rows) else {
+            self.clear(Cell::BLANK);
+            for (x, c) in too_small_message(frame).chars().enumerate() {
+                if let Some(col) = self.screen.get_mut(x) {
+                    col[0] = Cell::new(c, Style::DEFAULT);
+                }
+            }
+            self.last_frame = None;
+            return Ok(stats);
+        };
+
+        if self.last_frame.is_none() {
+            let background = match self.mode {
+                ColorMode::Color => Style {
+                    bg: Color::Blue,
+                    ..Style::DEFAULT
+                },
+                ColorMode::Monochrome => Style::DEFAULT,
+            };
+            self.clear(Cell::new(' ', background));
+        }
+
+        let (left, top) = (left as usize, top as usize);
+        for run in changed_runs(self.last_frame.as_ref(), frame, &mut stats) {
+            for (x, col) in frame.iter().enumerate().skip(run.x).take(run.len) {
+                self.screen[left + x][top + run.y] = col[run.y];
+            }
+        }
+
+        self.last_frame = Some(frame.clone());
+        Ok(stats)
+    }
+
+    fn resize(&mut self, term_cols: u16, term_rows: u16) {
+        self.screen = vec![vec![Cell::BLANK; term_rows as usize]; term_cols as usize];
+        self.last_frame = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{self, Color};
+    use crate::playfield::Playfield;
+
+    fn frame_with_text(text: &str) -> Frame {
+        let mut frame = frame::new_frame(Playfield { cols: 10, rows: 3 });
+        frame::draw_text(&mut frame, 1, 1, text, Style::fg(Color::Green).bold());
+        frame
+    }
+
+    #[test]
+    fn frames_are_drawn_centered_on_the_background() {
+        let mut renderer = MemoryRenderer::new(14, 5, ColorMode::Color);
+        renderer.render(&frame_with_text("HELLO")).unwrap();
+
+        assert_eq!(renderer.line(2), "   HELLO      ");
+        assert_eq!(
+            renderer.cell(3, 2),
+            Some(Cell::new('H', Style::fg(Color::Green).bold()))
+        );
+        // Inside the frame the cells are its own, outside is the background
+        assert_eq!(renderer.cell(2, 1), Some(Cell::BLANK));
+        assert_eq!(renderer.cell(0, 0).unwrap().style.bg, Color::Blue);
+        assert_eq!(renderer.cell(13, 4).unwrap().style.bg, Color::Blue);
+        assert_eq!(renderer.cell(14, 0), None);
+        assert_eq!(renderer.lines().len(), 5);
+    }
+
+    #[test]
+    fn monochrome_has_no_background_color() {
+        let mut renderer = MemoryRenderer::new(14, 5, ColorMode::Monochrome);
+        renderer.render(&frame_with_text("HELLO")).unwrap();
+        assert_eq!(renderer.cell(0, 0), Some(Cell::BLANK));
+    }
+
+    #[test]
+    fn only_changes_are_drawn_until_a_resize() {
+        let mut renderer = MemoryRenderer::new(14, 5, ColorMode::Color);
+        let first = renderer.render(&frame_with_text("HELLO")).unwrap();
+        assert_eq!(first.cells_written, 30);
+
+        let second = renderer.render(&frame_with_text("HELLA")).unwrap();
+        assert_eq!(second.cells_changed, 1);
+        assert_eq!(second.runs, 1);
+        assert_eq!(renderer.line(2), "   HELLA      ");
+
+        renderer.resize(12, 3);
+        let third = renderer.render(&frame_with_text("HELLA")).unwrap();
+        assert_eq!(third.cells_written, 30);
+        assert_eq!(renderer.line(1), "  HELLA     ");
+    }
+
+    #[test]
+    fn a_small_terminal_shows_what_is_needed() {
+        let mut renderer = MemoryRenderer::new(40, 2, ColorMode::Color);
+        renderer.render(&frame_with_text("HELLO")).unwrap();
+        assert_eq!(
+            renderer.line(0).trim_end(),
+            "Terminal too small, needs 10x3"
+        );
+        assert_eq!(renderer.line(1).trim_end(), "");
+
+        // Everything is drawn once it fits again
+        renderer.resize(10, 3);
+        let stats = renderer.render(&frame_with_text("HELLO")).unwrap();
+        assert_eq!(stats.cells_written, 30);
+        assert_eq!(renderer.line(1), " HELLO    ");
+    }
+}
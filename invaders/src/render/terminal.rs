@@ -0,0 +1,127 @@
+use super::{changed_runs, origin, too_small_message, ColorMode, RenderStats, Renderer};
+use crate::frame::{self, Frame, Style};
+use crossterm::cursor::MoveTo;
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::QueueableCommand;
+use std::io::{self, Write};
+
+// Draws with crossterm escape sequences on any writer, usually stdout
+pub struct TerminalRenderer<W: Write> {
+    out: W,
+    mode: ColorMode,
+    term_size: (u16, u16),
+    // None when the next frame must be drawn in full
+    last_frame: Option<Frame>,
+    showing_message: bool,
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    pub fn new(out: W, term_cols: u16, term_rows: u16, mode: ColorMode) -> Self {
+        Self {
+            out,
+            mode,
+            term_size: (term_cols, term_rows),
+            last_frame: None,
+            showing_message: false,
+        }
+    }
+}
+
+impl<W: Write> Renderer for TerminalRenderer<W> {
+    fn render(&mut self, frame: &Frame) -> io::Result<RenderStats> {
+        // Everything goes to the writer at once
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut stats = RenderStats {
+            frames: 1,
+            ..RenderStats::default()
+        };
+
+        let (term_cols, term_rows) = self.term_size;
+        let Some((left, top)) = origin(frame, term_cols, term_rows) else {
+            if !self.showing_message {
+                buffer.queue(SetAttribute(Attribute::Reset))?;
+                buffer.queue(Clear(ClearType::All))?;
+                buffer.queue(MoveTo(0, 0))?;
+                write!(buffer, "{}", too_small_message(frame))?;
+                self.showing_message = true;
+                self.last_frame = None;
+            }
+            return self.flush(buffer, stats);
+        };
+        self.showing_message = false;
+
+        if self.last_frame.is_none() {
+            buffer.queue(SetAttribute(Attribute::Reset))?;
+            if self.mode == ColorMode::Color {
+                buffer.queue(SetBackgroundColor(Color::Blue))?;
+            }
+            buffer.queue(Clear(ClearType::All))?;
+        }
+
+        // Only switch styles when the next cell needs a different one
+        let mut current_style = None;
+        for run in changed_runs(self.last_frame.as_ref(), frame, &mut stats) {
+            buffer.queue(MoveTo(left + run.x as u16, top + run.y as u16))?;
+            for col in frame.iter().skip(run.x).take(run.len) {
+                let cell = col[run.y];
+                if current_style != Some(cell.style) {
+                    queue_style(&mut buffer, cell.style, self.mode)?;
+                    current_style = Some(cell.style);
+                }
+                write!(buffer, "{}", cell.glyph)?;
+            }
+        }
+
+        self.last_frame = Some(frame.clone());
+        self.flush(buffer, stats)
+    }
+
+    fn resize(&mut self, term_cols: u16, term_rows: u16) {
+        self.term_size = (term_cols, term_rows);
+        self.last_frame = None;
+        self.showing_message = false;
+    }
+}
+
+impl<W: Write> TerminalRenderer<W> {
+    fn flush(&mut self, buffer: Vec<u8>, mut stats: RenderStats) -> io::Result<RenderStats> {
+        self.out.write_all(&buffer)?;
+        self.out.flush()?;
+        stats.bytes_written = buffer.len();
+        Ok(stats)
+    }
+}
+
+fn queue_style(buffer: &mut Vec<u8>, style: Style, mode: ColorMode) -> io::Result<()> {
+    // Resetting first, as attributes cannot be turned off one by one everywhere
+    buffer.queue(SetAttribute(Attribute::Reset))?;
+    if mode == ColorMode::Color {
+        buffer.queue(SetForegroundColor(terminal_color(style.fg)))?;
+        buffer.queue(SetBackgroundColor(terminal_color(style.bg)))?;
+    }
+    if style.attrs.bold {
+        buffer.queue(SetAttribute(Attribute::Bold))?;
+    }
+    if style.attrs.dim {
+        buffer.queue(SetAttribute(Attribute::Dim))?;
+    }
+    if style.attrs.reverse {
+        buffer.queue(SetAttribute(Attribute::Reverse))?;
+    }
+    Ok(())
+}
+
+fn terminal_color(color: frame::Color) -> Color {
+    match color {
+        frame::Color::Black => Color::Black,
+        frame::Color::DarkGrey => Color::DarkGrey,
+        frame::Color::Red => Color::Red,
+        frame::Color::Green => Color::Green,
+        frame::Color::Yellow => Color::Yellow,
+        frame::Color::Blue => Color::Blue,
+        frame::Color::Magenta => Color::Magenta,
+        frame::Color::Cyan => Color::Cyan,
+        frame::Color::White => Color::White,
+    }
+}
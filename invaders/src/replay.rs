@@ -0,0 +1,328 @@
+use crate::game::{Command, MAX_PLAYERS};
+use crate::playfield::Playfield;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+// First line of the file, bumped whenever the format changes or the same commands would play out
+// differently
+const HEADER: &str = "invaders-replay v4";
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "{}", err),
+            ReplayError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
+
+fn syntax_error(line: usize, message: impl Into<String>) -> ReplayError {
+    ReplayError::Syntax {
+        line,
+        message: message.into(),
+    }
+}
+
+// Commands given before one call to `Game::update`, each with its player, and the delta it was
+// called with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub delta: Duration,
+    pub commands: Vec<(usize, Command)>,
+}
+
+// Everything needed to play a game again exactly as it went
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub players: usize,
+    pub lives: u32,
+    pub playfield: Playfield,
+    // Text of the levels file the game was played with, the built-in levels when None. Kept
+    // whole, as the file may change or be gone by the time the game is played again.
+    pub levels: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+impl Replay {
+    pub fn new(
+        seed: u64,
+        players: usize,
+        lives: u32,
+        playfield: Playfield,
+        levels: Option<String>,
+    ) -> Self {
+        Self {
+            seed,
+            players,
+            lives,
+            playfield,
+            levels,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, delta: Duration, commands: Vec<(usize, Command)>) {
+        self.steps.push(Step { delta, commands });
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ReplayError> {
+        let mut lines = text.lines().enumerate().map(|(idx, line)| (idx + 1, line));
+        match lines.next() {
+            Some((_, line)) if line.trim() == HEADER => {}
+            _ => return Err(syntax_error(1, format!("expected `{}`", HEADER))),
+        }
+
+        let mut seed = None;
+        // A single player when missing
+        let mut players = 1;
+        let mut lives = None;
+        let mut playfield = None;
+        let mut levels = None;
+        let mut steps = Vec::new();
+        while let Some((line_no, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| syntax_error(line_no, format!("`{}` is not a number", value)))
+            };
+            match key {
+                "seed" => seed = Some(number(value)?),
+                "players" => {
+                    players = number(value)? as usize;
+                    if !(1..=MAX_PLAYERS).contains(&players) {
+                        return Err(syntax_error(
+                            line_no,
+                            format!("{} players are not supported", players),
+                        ));
+                    }
+                }
+                "lives" => {
+                    let count = u32::try_from(number(value)?)
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| {
+                            syntax_error(line_no, format!("{} lives are not supported", value))
+                        })?;
+                    lives = Some(count);
+                }
+                "playfield" => {
+                    let (cols, rows) = value.split_once(' ').unwrap_or((value, ""));
+                    playfield = Some(Playfield {
+                        cols: number(cols)? as usize,
+                        rows: number(rows)? as usize,
+                    });
+                }
+                // The number of lines of the levels file, which follow as they are
+                "levels" => {
+                    let count = number(value)? as usize;
+                    let text: Vec<&str> =
+                        lines.by_ref().take(count).map(|(_, line)| line).collect();
+                    if text.len() < count {
+                        return Err(syntax_error(line_no, "replay ends within the levels"));
+                    }
+                    levels = Some(text.iter().map(|line| format!("{}\n", line)).collect());
+                }
+                "step" => {
+                    let (delta, commands) = value.split_once(' ').unwrap_or((value, ""));
+                    let commands = commands
+                        .chars()
+                        .map(|c| {
+                            command_from_char(c).ok_or_else(|| {
+                                syntax_error(line_no, format!("unknown command `{}`", c))
+                            })
+                        })
+                        .collect::<Result<_, _>>()?;
+                    steps.push(Step {
+                        delta: Duration::from_nanos(number(delta)?),
+                        commands,
+                    });
+                }
+                _ => return Err(syntax_error(line_no, format!("unknown entry `{}`", key))),
+            }
+        }
+
+        let missing = |key: &str| syntax_error(1, format!("replay has no `{}`", key));
+        let playfield = playfield.ok_or_else(|| missing("playfield"))?;
+        if !playfield.fits_in(Playfield::MAX.cols as u16, Playfield::MAX.rows as u16)
+            || playfield.cols < Playfield::MIN.cols
+            || playfield.rows < Playfield::MIN.rows
+        {
+            return Err(syntax_error(
+                1,
+                format!(
+                    "playfield {}x{} is not supported",
+                    playfield.cols, playfield.rows
+                ),
+            ));
+        }
+        Ok(Self {
+            seed: seed.ok_or_else(|| missing("seed"))?,
+            players,
+            lives: lives.ok_or_else(|| missing("lives"))?,
+            playfield,
+            levels,
+            steps,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+}
+
+impl Display for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "players {}", self.players)?;
+        writeln!(f, "lives {}", self.lives)?;
+        writeln!(
+            f,
+            "playfield {} {}",
+            self.playfield.cols, self.playfield.rows
+        )?;
+        if let Some(levels) = &self.levels {
+            writeln!(f, "levels {}", levels.lines().count())?;
+            for line in levels.lines() {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        // Deltas in nanoseconds, the resolution of `Duration`, so that timers tick exactly the same
+        for step in self.steps.iter() {
+            write!(f, "step {}", step.delta.as_nanos())?;
+            if !step.commands.is_empty() {
+                let commands: String = step
+                    .commands
+                    .iter()
+                    .map(|(player, command)| command_char(*player, *command))
+                    .collect();
+                write!(f, " {}", commands)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+// Upper case for the first player, lower case for the second
+fn command_char(player: usize, command: Command) -> char {
+    let c = match command {
+        Command::MoveLeft => 'L',
+        Command::MoveRight => 'R',
+        Command::Shoot => 'S',
+    };
+    if player == 0 {
+        c
+    } else {
+        c.to_ascii_lowercase()
+    }
+}
+
+fn command_from_char(c: char) -> Option<(usize, Command)> {
+    let player = if c.is_ascii_lowercase() { 1 } else { 0 };
+    match c.to_ascii_uppercase() {
+        'L' => Some((player, Command::MoveLeft)),
+        'R' => Some((player, Command::MoveRight)),
+        'S' => Some((player, Command::Shoot)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::Levels;
+
+    // As written in a file, with a comment after the wave
+    fn levels() -> String {
+        format!("{}\n# end\n", Levels::single_text("A.B", 500, 1, 700))
+    }
+
+    fn recording(levels: Option<String>) -> Replay {
+        let mut replay = Replay::new(7, 2, 3, Playfield { cols: 44, rows: 22 }, levels);
+        replay.push(Duration::from_nanos(16_666_666), Vec::new());
+        replay.push(
+            Duration::from_nanos(16_666_667),
+            vec![
+                (0, Command::MoveLeft),
+                (1, Command::Shoot),
+                (0, Command::Shoot),
+            ],
+        );
+        replay.push(Duration::from_millis(5), vec![(1, Command::MoveRight)]);
+        replay
+    }
+
+    fn syntax_line(err: ReplayError) -> usize {
+        match err {
+            ReplayError::Syntax { line, .. } => line,
+            ReplayError::Io(err) => panic!("unexpected {}", err),
+        }
+    }
+
+    #[test]
+    fn saved_replays_parse_back_the_same() {
+        for levels in [None, Some(levels())] {
+            let replay = recording(levels);
+            assert_eq!(Replay::parse(&replay.to_string()).unwrap(), replay);
+        }
+    }
+
+    #[test]
+    fn levels_are_kept_whole_in_the_replay() {
+        let text = recording(Some(levels())).to_string();
+        assert!(text.contains("levels 9\n[level]\nmove = 500\n"));
+        assert!(text.contains("A.B\n\n# end\nstep "));
+    }
+
+    #[test]
+    fn replays_ending_within_the_levels_are_rejected() {
+        let text = format!(
+            "{}\nseed 1\nlives 3\nplayfield 40 20\nlevels 3\n[level]\n",
+            HEADER
+        );
+        assert_eq!(syntax_line(Replay::parse(&text).unwrap_err()), 5);
+    }
+
+    #[test]
+    fn lives_must_fit_and_be_more_than_zero() {
+        for lives in ["0", "4294967296"] {
+            let text = format!("{}\nseed 1\nlives {}\nplayfield 40 20\n", HEADER, lives);
+            assert_eq!(syntax_line(Replay::parse(&text).unwrap_err()), 3);
+        }
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let text = "invaders-replay v3\nseed 1\nlives 3\nplayfield 40 20\n";
+        assert_eq!(syntax_line(Replay::parse(text).unwrap_err()), 1);
+    }
+}
@@ -0,0 +1,70 @@
+use crate::animation::{Animation, Repeat};
+use crate::frame::{Drawable, Frame};
+use crate::invaders::{InvaderKind, InvaderSize};
+use crate::playfield::Playfield;
+use crate::SAUCER_ROW;
+use rusty_time::Timer;
+use std::time::Duration;
+
+// Bonus a saucer can be worth, one is picked when it appears
+pub const SAUCER_POINTS: [u32; 4] = [50, 100, 150, 300];
+
+// Mystery ship crossing the top row of the playfield for bonus points
+pub struct Saucer {
+    pub x: usize,
+    pub y: usize,
+    pub points: u32,
+    // Positive move to right, negative move to left
+    direction: i32,
+    move_timer: Timer,
+    gone: bool,
+    cols: usize,
+    // Index of the sprite shown
+    spin: Animation<usize>,
+}
+
+impl Saucer {
+    pub fn new(from_left: bool, points: u32, playfield: Playfield) -> Self {
+        Self {
+            x: if from_left { 0 } else { playfield.cols - 1 },
+            y: SAUCER_ROW,
+            points,
+            direction: if from_left { 1 } else { -1 },
+            move_timer: Timer::new(Duration::from_millis(150)),
+            gone: false,
+            cols: playfield.cols,
+            spin: Animation::even(&[0, 1], Duration::from_millis(200), Repeat::Loop),
+        }
+    }
+
+    pub fn update(&mut self, delta: Duration) {
+        self.spin.update(delta);
+        self.move_timer.tick(delta);
+        if self.move_timer.finished() {
+            self.move_timer.reset();
+            let x = self.x as i32 + self.direction;
+            if (0..self.cols as i32).contains(&x) {
+                self.x = x as usize;
+            } else {
+                self.gone = true;
+            }
+        }
+    }
+
+    // Flew off the other side
+    pub fn gone(&self) -> bool {
+        self.gone
+    }
+}
+
+impl Drawable for Saucer {
+    fn draw(&self, frame: &mut Frame) {
+        let kind = InvaderKind::Saucer;
+        kind.sprites(InvaderSize::Small)[self.spin.current()].draw(
+            frame,
+            self.x,
+            self.y,
+            kind.style(),
+        );
+    }
+}
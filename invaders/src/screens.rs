@@ -0,0 +1,147 @@
+use crate::frame::{self, Cell, Color, Drawable, Frame, Layer, Style};
+
+// What the front-end shows, the game only runs while Playing
+pub enum Screen {
+    Title(Menu),
+    Playing,
+    // Drawn over the frozen game
+    Paused(Menu),
+    GameOver(Menu),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Choice {
+    Start,
+    // The game plays itself
+    Demo,
+    Resume,
+    Restart,
+    Quit,
+}
+
+impl Choice {
+    fn label(self) -> &'static str {
+        match self {
+            Choice::Start => "START",
+            Choice::Demo => "DEMO",
+            Choice::Resume => "RESUME",
+            Choice::Restart => "PLAY AGAIN",
+            Choice::Quit => "QUIT",
+        }
+    }
+}
+
+// A heading, a few lines of information and the choices, centered on the playfield
+pub struct Menu {
+    heading: String,
+    info: Vec<String>,
+    choices: Vec<Choice>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(heading: &str, info: Vec<String>, choices: Vec<Choice>) -> Self {
+        Self {
+            heading: heading.to_string(),
+            info,
+            choices,
+            selected: 0,
+        }
+    }
+
+    pub fn title(high_score: u32) -> Self {
+        Self::new(
+            "SPACE INVADERS",
+            vec![format!("HI {:06}", high_score)],
+            vec![Choice::Start, Choice::Demo, Choice::Quit],
+        )
+    }
+
+    pub fn pause() -> Self {
+        Self::new("PAUSED", Vec::new(), vec![Choice::Resume, Choice::Quit])
+    }
+
+    pub fn game_over(score: u32, level: u32) -> Self {
+        Self::new(
+            "GAME OVER",
+            vec![
+                format!("SCORE {:06}", score),
+                format!("REACHED LEVEL {}", level),
+            ],
+            vec![Choice::Restart, Choice::Quit],
+        )
+    }
+
+    pub fn up(&mut self) {
+        self.selected = self
+            .selected
+            .checked_sub(1)
+            .unwrap_or(self.choices.len() - 1);
+    }
+
+    pub fn down(&mut self) {
+        self.selected = (self.selected + 1) % self.choices.len();
+    }
+
+    pub fn selected(&self) -> Choice {
+        self.choices[self.selected]
+    }
+
+    fn lines(&self) -> usize {
+        // Heading, blank line, info, blank line, choices
+        2 + self.info.len() + usize::from(!self.info.is_empty()) + self.choices.len()
+    }
+}
+
+impl Drawable for Menu {
+    fn draw(&self, frame: &mut Frame) {
+        let rows = frame.first().map(|col| col.len()).unwrap_or(0);
+        let top = rows.saturating_sub(self.lines()) / 2;
+
+        // Blank box behind the text, so that it stays readable over the game
+        let width = self
+            .info
+            .iter()
+            .map(|line| line.len())
+            .chain(self.choices.iter().map(|choice| choice.label().len()))
+            .chain([self.heading.len()])
+            .max()
+            .unwrap_or(0)
+            + 6;
+        let left = frame.len().saturating_sub(width) / 2;
+        for col in frame.iter_mut().skip(left).take(width) {
+            for cell in col
+                .iter_mut()
+                .skip(top.saturating_sub(1))
+                .take(self.lines() + 2)
+            {
+                *cell = Cell::BLANK;
+            }
+        }
+
+        let mut y = top;
+        frame::draw_text_centered(frame, y, &self.heading, Style::fg(Color::Yellow).bold());
+        y += 2;
+        for line in self.info.iter() {
+            frame::draw_text_centered(frame, y, line, Style::DEFAULT);
+            y += 1;
+        }
+        if !self.info.is_empty() {
+            y += 1;
+        }
+        for (idx, choice) in self.choices.iter().enumerate() {
+            let label = format!(" {} ", choice.label());
+            let style = if idx == self.selected {
+                Style::fg(Color::Green).bold().reverse()
+            } else {
+                Style::DEFAULT
+            };
+            frame::draw_text_centered(frame, y, &label, style);
+            y += 1;
+        }
+    }
+
+    fn layer(&self) -> Layer {
+        Layer::Overlay
+    }
+}
@@ -1,29 +1,77 @@
-use crate::frame::{Drawable, Frame};
+use crate::animation::{Animation, Repeat};
+use crate::frame::{Cell, Color, Drawable, Frame, Style};
+use crate::playfield::Playfield;
+use crate::HUD_ROW;
 use rusty_time::Timer;
 use std::time::Duration;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    // Fired by the player
+    Up,
+    // Dropped by the invaders
+    Down,
+}
+
 pub struct Shot {
     pub x: usize,
     pub y: usize,
+    pub direction: Direction,
     pub exploding: bool,
+    // Goes through invaders instead of exploding on them
+    pub piercing: bool,
+    // Ids of the invaders a piercing shot went through, each only takes one hit from it
+    pub pierced: Vec<usize>,
+    // Last row a bomb can reach
+    bottom: usize,
     timer: Timer,
+    explosion: Animation<char>,
 }
 
 impl Shot {
-    pub fn new(x: usize, y: usize) -> Self {
+    pub fn new(x: usize, y: usize, direction: Direction, playfield: Playfield) -> Self {
+        let speed = match direction {
+            Direction::Up => Duration::from_millis(35),
+            Direction::Down => Duration::from_millis(100),
+        };
         Self {
             x,
             y,
+            direction,
             exploding: false,
-            timer: Timer::new(Duration::from_millis(35)),
+            piercing: false,
+            pierced: Vec::new(),
+            bottom: playfield.bottom(),
+            timer: Timer::new(speed),
+            explosion: Animation::new(
+                vec![
+                    ('*', Duration::from_millis(100)),
+                    ('+', Duration::from_millis(80)),
+                    ('.', Duration::from_millis(70)),
+                ],
+                Repeat::Once,
+            ),
         }
     }
 
     pub fn update(&mut self, delta: Duration) {
+        if self.exploding {
+            self.explosion.update(delta);
+            return;
+        }
         self.timer.tick(delta);
-        if self.timer.finished() && !self.exploding {
-            if self.y > 0 {
-                self.y -= 1;
+        if self.timer.finished() {
+            match self.direction {
+                Direction::Up => {
+                    if self.y > HUD_ROW {
+                        self.y -= 1;
+                    }
+                }
+                Direction::Down => {
+                    if self.y < self.bottom {
+                        self.y += 1;
+                    }
+                }
             }
             self.timer.reset();
         }
@@ -31,16 +79,55 @@ impl Shot {
 
     pub fn explode(&mut self) {
         self.exploding = true;
-        self.timer = Timer::new(Duration::from_millis(250));
     }
 
     pub fn dead(&self) -> bool {
-        (self.exploding && self.timer.finished()) || (self.y == 0)
+        let off_screen = match self.direction {
+            Direction::Up => self.y == HUD_ROW,
+            Direction::Down => self.y == self.bottom,
+        };
+        (self.exploding && self.explosion.finished()) || (!self.exploding && off_screen)
+    }
+}
+
+// Half of the cell something moving in the given direction is in, given how far it is on its way
+// to the next cell
+pub(crate) fn half_row(y: usize, direction: Direction, progress: f32) -> usize {
+    let second_half = progress >= 0.5;
+    match direction {
+        Direction::Up if second_half => 2 * y,
+        Direction::Up => 2 * y + 1,
+        Direction::Down if second_half => 2 * y + 1,
+        Direction::Down => 2 * y,
+    }
+}
+
+impl Shot {
+    fn cell(&self) -> Cell {
+        match (self.exploding, self.direction) {
+            (true, _) => Cell::new(self.explosion.current(), Style::fg(Color::Yellow).bold()),
+            (false, Direction::Up) if self.piercing => {
+                Cell::new('|', Style::fg(Color::Magenta).bold())
+            }
+            (false, Direction::Up) => Cell::new('|', Style::fg(Color::White)),
+            (false, Direction::Down) => Cell::new('!', Style::fg(Color::Red)),
+        }
     }
 }
 
 impl Drawable for Shot {
     fn draw(&self, frame: &mut Frame) {
-        frame[self.x][self.y] = if self.exploding { "*" } else { "|" };
+        frame[self.x][self.y] = self.cell();
+    }
+
+    // Moving shots take half a cell, explosions the whole cell
+    fn draw_half_blocks(&self, pixels: &mut Frame) {
+        if self.exploding {
+            pixels[self.x][2 * self.y] = self.cell();
+            pixels[self.x][2 * self.y + 1] = self.cell();
+        } else {
+            let row = half_row(self.y, self.direction, self.timer.percent());
+            pixels[self.x][row] = self.cell();
+        }
     }
 }
@@ -0,0 +1,148 @@
+use crate::controller::{Bot, Controller};
+use crate::game::{Game, GameEvent, Settings, TICK};
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+
+// Longest a simulated game may last, a bot surviving forever does not win
+const MAX_GAME_TIME: Duration = Duration::from_secs(30 * 60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Outcome {
+    // The last defined level was cleared
+    pub won: bool,
+    pub score: u32,
+    pub level: u32,
+}
+
+// Plays a whole game without a terminal, with a bot for each player
+pub fn play(settings: Settings) -> Outcome {
+    let last_level = settings.levels.len() as u32;
+    let mut bots: Vec<Bot> = (0..settings.players).map(|_| Bot::new()).collect();
+    let mut game = Game::new(settings);
+    let mut won = false;
+    let mut time = Duration::ZERO;
+
+    while !won && !game.is_over() && time < MAX_GAME_TIME {
+        for (player, bot) in bots.iter_mut().enumerate() {
+            for command in bot.commands(&game, player) {
+                game.command(player, command);
+            }
+        }
+        game.update(TICK);
+        time += TICK;
+        won = game
+            .drain_events()
+            .any(|event| matches!(event, GameEvent::LevelCleared { level } if level >= last_level));
+    }
+
+    Outcome {
+        won,
+        score: game.score(),
+        level: game.level(),
+    }
+}
+
+// Totals over several simulated games
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub games: u32,
+    pub wins: u32,
+    pub total_score: u64,
+    pub total_level: u64,
+}
+
+impl Report {
+    pub fn add(&mut self, outcome: Outcome) {
+        self.games += 1;
+        self.wins += u32::from(outcome.won);
+        self.total_score += u64::from(outcome.score);
+        self.total_level += u64::from(outcome.level);
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let games = f64::from(self.games.max(1));
+        write!(
+            f,
+            "{} games, {} won ({:.1}%), average score {:.1}, average level reached {:.1}",
+            self.games,
+            self.wins,
+            f64::from(self.wins) * 100.0 / games,
+            self.total_score as f64 / games,
+            self.total_level as f64 / games,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::levels::Levels;
+    use crate::playfield::Playfield;
+
+    fn settings(seed: u64, levels: Levels) -> Settings {
+        Settings {
+            seed,
+            levels,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn a_game_ends_the_same_way_for_the_same_seed() {
+        let outcome = play(settings(11, Levels::builtin()));
+        assert!(outcome.level >= 1);
+        assert!(outcome.score > 0);
+        assert_eq!(play(settings(11, Levels::builtin())), outcome);
+    }
+
+    #[test]
+    fn clearing_the_last_level_wins() {
+        let outcome = play(settings(3, Levels::single("A.B", 2000, 1, 100_000)));
+        assert_eq!(
+            outcome,
+            Outcome {
+                won: true,
+                score: 50,
+                level: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn a_game_nobody_can_end_stops_at_the_time_limit() {
+        // Nobody fires, the army never moves and its only invader is in the first column, out of
+        // reach of the middle of the cannon
+        let wave = format!("A{}", ".".repeat(Playfield::MIN.cols - 1));
+        let levels = Levels::single(&wave, 100_000_000, 1, 100_000_000);
+        let outcome = play(settings(5, levels));
+        assert_eq!(
+            outcome,
+            Outcome {
+                won: false,
+                score: 0,
+                level: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn reports_average_over_the_games() {
+        let mut report = Report::default();
+        report.add(Outcome {
+            won: true,
+            score: 100,
+            level: 3,
+        });
+        report.add(Outcome {
+            won: false,
+            score: 50,
+            level: 2,
+        });
+        assert_eq!(
+            report.to_string(),
+            "2 games, 1 won (50.0%), average score 75.0, average level reached 2.5"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::frame::{Cell, Frame, Style};
+use crate::playfield::Playfield;
+
+// Cells from (x, y) on, right and down
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+// Glyphs of a sprite, one string per row. Spaces are see-through and cannot be hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    rows: &'static [&'static str],
+}
+
+impl Sprite {
+    pub const fn new(rows: &'static [&'static str]) -> Self {
+        Self { rows }
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Bounding box with the top left corner at the given position
+    pub fn bounds(&self, x: usize, y: usize) -> Rect {
+        Rect {
+            x,
+            y,
+            width: self.width(),
+            height: self.height(),
+        }
+    }
+
+    // Offsets and glyphs of the cells that are not see-through
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, char)> + '_ {
+        self.rows.iter().enumerate().flat_map(|(dy, row)| {
+            row.chars()
+                .enumerate()
+                .filter(|(_, c)| *c != ' ')
+                .map(move |(dx, c)| (dx, dy, c))
+        })
+    }
+
+    // Clipped to the frame
+    pub fn draw(&self, frame: &mut Frame, x: usize, y: usize, style: Style) {
+        for (dx, dy, c) in self.cells() {
+            if let Some(cell) = frame.get_mut(x + dx).and_then(|col| col.get_mut(y + dy)) {
+                *cell = Cell::new(c, style);
+            }
+        }
+    }
+}
+
+// Which entity covers each cell, so that collisions are found without going through all of them.
+// Rebuilt whenever the entities move.
+pub struct SpatialGrid {
+    rows: usize,
+    cells: Vec<Option<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(playfield: Playfield) -> Self {
+        Self {
+            rows: playfield.rows,
+            cells: vec![None; playfield.cols * playfield.rows],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(None);
+    }
+
+    // Only the cells of the sprite that can be hit, and that are on the playfield
+    pub fn insert(&mut self, idx: usize, x: usize, y: usize, sprite: &Sprite) {
+        for (dx, dy, _) in sprite.cells() {
+            if let Some(slot) = self.slot(x + dx, y + dy) {
+                self.cells[slot] = Some(idx);
+            }
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<usize> {
+        self.slot(x, y).and_then(|slot| self.cells[slot])
+    }
+
+    fn slot(&self, x: usize, y: usize) -> Option<usize> {
+        let slot = x * self.rows + y;
+        (y < self.rows && slot < self.cells.len()).then_some(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LARGE: Sprite = Sprite::new(&["/o\\", "/ \\"]);
+
+    #[test]
+    fn see_through_cells_cannot_be_hit() {
+        let mut grid = SpatialGrid::new(Playfield::MIN);
+        grid.insert(7, 5, 3, &LARGE);
+        for (x, y) in [(5, 3), (6, 3), (7, 3), (5, 4), (7, 4)] {
+            assert_eq!(grid.get(x, y), Some(7), "({}, {})", x, y);
+        }
+        assert_eq!(grid.get(6, 4), None);
+        assert_eq!(grid.get(8, 3), None);
+        assert_eq!(grid.get(5, 5), None);
+    }
+
+    #[test]
+    fn cells_past_the_edges_are_left_out() {
+        let playfield = Playfield { cols: 10, rows: 5 };
+        let mut grid = SpatialGrid::new(playfield);
+        // The bottom row of the sprite is below the playfield, it must not wrap to the next column
+        grid.insert(1, 7, 4, &LARGE);
+        assert_eq!(grid.get(7, 4), Some(1));
+        assert_eq!(grid.get(8, 0), None);
+        assert_eq!(grid.get(9, 0), None);
+        // Past the right side
+        grid.insert(2, 8, 0, &LARGE);
+        assert_eq!(grid.get(9, 0), Some(2));
+        assert_eq!(grid.get(10, 0), None);
+        assert_eq!(grid.get(0, 5), None);
+
+        grid.clear();
+        assert_eq!(grid.get(7, 4), None);
+    }
+
+    #[test]
+    fn touching_rects_do_not_intersect() {
+        let rect = LARGE.bounds(2, 2);
+        assert_eq!(
+            rect,
+            Rect {
+                x: 2,
+                y: 2,
+                width: 3,
+                height: 2
+            }
+        );
+        assert!(rect.contains(4, 3));
+        assert!(!rect.contains(5, 3));
+        assert!(rect.intersects(&LARGE.bounds(4, 3)));
+        assert!(!rect.intersects(&LARGE.bounds(5, 2)));
+        assert!(!rect.intersects(&LARGE.bounds(2, 4)));
+    }
+}